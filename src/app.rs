@@ -0,0 +1,197 @@
+use crate::args::Mode;
+use crate::config::Config;
+use crate::one_shot::{FILE_LINGER, OneShot};
+use anyhow::{Context, Result};
+use libp2p::Multiaddr;
+use p2p_chat::models::client::{Client, Exit};
+#[cfg(unix)]
+use p2p_chat::models::control::server::ControlServer;
+use p2p_chat::models::identity::identity_manager::IdentityManager;
+use p2p_chat::{ChatNode, NodeEvents, NodeHandle, RunningNode};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::task::{JoinError, JoinHandle};
+
+/// Peer addresses dialed on every start, one per line
+const BOOTSTRAP_FILE_NAME: &str = "bootstrap.txt";
+/// Addresses to listen on when neither flags nor the config file give them, one per line
+const LISTEN_FILE_NAME: &str = "listen.txt";
+/// Lines entered in the line mode, in the data dir
+const INPUT_HISTORY_FILE_NAME: &str = "input_history.txt";
+
+/// Command line client or control API on top of a ChatNode
+pub struct App {
+    frontend: Frontend,
+    swarm_task: JoinHandle<()>,
+}
+
+/// What drives the node until the user stops it
+enum Frontend {
+    Client(Box<Client>),
+    #[cfg(unix)]
+    Daemon(ControlServer),
+    OneShot(OneShot, NodeHandle, NodeEvents),
+}
+
+impl App {
+    /// Starts the node with the effective configuration and the front end of the mode
+    pub fn new(
+        config: Config,
+        mode: Option<Mode>,
+        passphrase: Option<String>,
+        socket: PathBuf,
+    ) -> Result<Self> {
+        #[cfg(not(unix))]
+        if matches!(mode, Some(Mode::Daemon)) {
+            anyhow::bail!("Daemon mode needs Unix domain sockets");
+        }
+
+        let Config {
+            network,
+            discovery,
+            storage,
+            ui,
+            ..
+        } = config.clone();
+
+        let identity = IdentityManager::load_or_generate(storage.identity, passphrase)?;
+        log::info!("Local peer id: {}", identity.peer_id());
+
+        let mut peers = load_addr_list(&storage.data_dir.join(BOOTSTRAP_FILE_NAME))?;
+        peers.extend(network.peers);
+
+        let listen_addrs = match network.listen.is_empty() {
+            true => load_addr_list(&storage.data_dir.join(LISTEN_FILE_NAME))?,
+            false => network.listen,
+        };
+
+        let RunningNode {
+            handle,
+            events,
+            task,
+        } = ChatNode::new(identity.keypair().clone())
+            .with_listen_addrs(listen_addrs)
+            .with_peers(peers)
+            .with_mdns(discovery.mdns)
+            .with_heartbeat_interval(config.heartbeat_interval())
+            .with_buffer_size(network.buffer_size)
+            .with_data_dir(&storage.data_dir)
+            .with_download_dir(storage.download_dir)
+            .with_nick(ui.nick)
+            .with_short_id_len(ui.short_id_len)
+            .start()?;
+
+        let frontend = match mode {
+            #[cfg(unix)]
+            Some(Mode::Daemon) => Frontend::Daemon(ControlServer::bind(socket, handle, events)?),
+            Some(Mode::Send {
+                topic,
+                message,
+                file,
+                key,
+                min_peers,
+                wait,
+                linger,
+            }) => {
+                let linger = match (linger, &file) {
+                    (Some(secs), _) => Duration::from_secs(secs),
+                    (None, Some(_)) => FILE_LINGER,
+                    (None, None) => Duration::ZERO,
+                };
+                let one_shot = OneShot {
+                    topic,
+                    text: message,
+                    file,
+                    key,
+                    min_peers,
+                    wait: Duration::from_secs(wait),
+                    linger,
+                    output: ui.output.into(),
+                };
+
+                Frontend::OneShot(one_shot, handle, events)
+            }
+            _ => Frontend::Client(Box::new(
+                Client::new(handle, events, identity, ui.tui)
+                    .with_input_history(storage.data_dir.join(INPUT_HISTORY_FILE_NAME))
+                    .with_response_timeout(config.response_timeout())
+                    .with_output(ui.output.into()),
+            )),
+        };
+
+        Ok(Self {
+            frontend,
+            swarm_task: task,
+        })
+    }
+
+    /// Runs until the front end stops and the swarm shuts down, returns the exit code
+    pub async fn run(self) -> u8 {
+        let mut swarm_task = self.swarm_task;
+        let mut client_task = tokio::spawn(self.frontend.run());
+
+        tokio::select! {
+            swarm_res = &mut swarm_task => {
+                if let Err(e) = swarm_res {
+                    log::error!("Error in swarm_manager: {e}. Shutting down...");
+                    return Exit::Failed.code();
+                }
+
+                // the swarm stops only when the client shuts it down
+                exit_code(client_task.await)
+            }
+            client_res = &mut client_task => {
+                let code = exit_code(client_res);
+
+                if let Err(e) = swarm_task.await {
+                    log::error!("Error in swarm_manager: {e}");
+                    return Exit::Failed.code();
+                }
+
+                code
+            }
+        }
+    }
+}
+
+impl Frontend {
+    async fn run(self) -> Exit {
+        match self {
+            Frontend::Client(client) => client.run().await,
+            #[cfg(unix)]
+            Frontend::Daemon(server) => server.run().await,
+            Frontend::OneShot(one_shot, node, events) => one_shot.run(node, events).await,
+        }
+    }
+}
+
+fn exit_code(client_res: Result<Exit, JoinError>) -> u8 {
+    match client_res {
+        Ok(exit) => exit.code(),
+        Err(e) => {
+            log::error!("Error in client: {e}. Shutting down...");
+            Exit::Failed.code()
+        }
+    }
+}
+
+/// Reads multiaddrs from the file, skipping blank lines and `#` comments.
+/// Missing file means empty list
+fn load_addr_list(path: &Path) -> Result<Vec<Multiaddr>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Couldn't read {}", path.display())),
+    };
+
+    content
+        .lines()
+        .enumerate()
+        .map(|(n, line)| (n + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(n, line)| {
+            line.parse()
+                .with_context(|| format!("Invalid address on line {n} of {}", path.display()))
+        })
+        .collect()
+}
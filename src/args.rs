@@ -0,0 +1,280 @@
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use libp2p::Multiaddr;
+use p2p_chat::models::client::Output;
+use p2p_chat::models::common::peer_name::validate_nick;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Flags override environment variables, which override the config file
+#[derive(Parser, Debug)]
+#[command(author, version, about)]
+pub struct Args {
+    /// TOML config file, p2p-chat/config.toml in the XDG config dir by default
+    #[arg(
+        long,
+        global = true,
+        env = "P2P_CHAT_CONFIG",
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath
+    )]
+    pub config: Option<PathBuf>,
+
+    /// Directory downloaded files are saved to [default: ./downloads]
+    #[arg(
+        short,
+        long,
+        env = "P2P_CHAT_DOWNLOAD_DIR",
+        value_name = "DIR",
+        value_hint = clap::ValueHint::DirPath
+    )]
+    pub dir: Option<PathBuf>,
+
+    /// Directory for message history and other local data [default: ./data]
+    #[arg(
+        long,
+        global = true,
+        env = "P2P_CHAT_DATA_DIR",
+        value_name = "DIR",
+        value_hint = clap::ValueHint::DirPath
+    )]
+    pub data_dir: Option<PathBuf>,
+
+    /// Key file with the node identity, created on first run [default: ./identity.key]
+    #[arg(
+        short,
+        long,
+        env = "P2P_CHAT_IDENTITY",
+        value_name = "PATH",
+        value_hint = clap::ValueHint::FilePath
+    )]
+    pub identity: Option<PathBuf>,
+
+    /// Passphrase used to encrypt the identity key file, a plain key file gets encrypted with it
+    #[arg(long, env = "P2P_CHAT_PASSPHRASE", hide_env_values = true)]
+    pub passphrase: Option<String>,
+
+    /// Address of a peer to dial on start, e.g. /ip4/10.0.0.5/tcp/4001, can be repeated.
+    /// Added to the peers of the config file
+    #[arg(
+        long = "peer",
+        env = "P2P_CHAT_PEERS",
+        value_delimiter = ',',
+        value_name = "MULTIADDR"
+    )]
+    pub peers: Vec<Multiaddr>,
+
+    /// Address to listen on, e.g. /ip4/0.0.0.0/tcp/4001 or /ip6/::/tcp/4001, can be repeated
+    #[arg(
+        long = "listen",
+        env = "P2P_CHAT_LISTEN",
+        value_delimiter = ',',
+        value_name = "MULTIADDR"
+    )]
+    pub listen: Vec<Multiaddr>,
+
+    /// Look for peers in the local network with mDNS [default: true]
+    #[arg(
+        long,
+        env = "P2P_CHAT_MDNS",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        overrides_with = "no_mdns",
+        value_name = "BOOL"
+    )]
+    mdns: Option<bool>,
+
+    /// Don't look for peers in the local network, same as --mdns=false
+    #[arg(long, overrides_with = "mdns")]
+    no_mdns: bool,
+
+    /// Milliseconds between gossipsub heartbeats [default: 10000]
+    #[arg(long, env = "P2P_CHAT_HEARTBEAT_INTERVAL", value_name = "MS")]
+    pub heartbeat_interval: Option<u64>,
+
+    /// Commands and events queued between the node and the front end [default: 30]
+    #[arg(long, env = "P2P_CHAT_BUFFER_SIZE", value_name = "N")]
+    pub buffer_size: Option<usize>,
+
+    /// Nickname announced to peers, can be changed with the nick command
+    #[arg(long, env = "P2P_CHAT_NICK", value_parser = validate_nick)]
+    pub nick: Option<String>,
+
+    /// Minimal number of trailing PeerId characters shown to identify a peer [default: 6]
+    #[arg(
+        long,
+        env = "P2P_CHAT_SHORT_ID_LEN",
+        value_name = "N",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(4..=16)
+    )]
+    pub short_id_len: Option<usize>,
+
+    /// Milliseconds to wait for a command before it is shown as pending,
+    /// its response is shown when it comes [default: 3000]
+    #[arg(long, env = "P2P_CHAT_RESPONSE_TIMEOUT", value_name = "MS")]
+    pub response_timeout: Option<u64>,
+
+    /// Full-screen terminal UI with a pane per topic, switches the output to text
+    /// unless `--output` is given and this comes from the environment.
+    /// Logs still go to stderr, redirect it when RUST_LOG is set [default: false]
+    #[arg(
+        long,
+        env = "P2P_CHAT_TUI",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        overrides_with = "no_tui",
+        value_name = "BOOL"
+    )]
+    tui: Option<bool>,
+
+    /// Line mode instead of the terminal UI, same as --tui=false
+    #[arg(long, overrides_with = "tui")]
+    no_tui: bool,
+
+    /// Format of the line mode and one-shot send output, JSON turns the terminal UI off
+    /// [default: text]
+    #[arg(long, env = "P2P_CHAT_OUTPUT", value_enum)]
+    pub output: Option<OutputFormat>,
+
+    /// Unix socket of the daemon, control.sock in the data dir by default
+    #[arg(long, global = true, value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+    pub socket: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub mode: Option<Mode>,
+}
+
+impl Args {
+    /// Parses the flags and the environment, exits on errors like [`Parser::parse`]
+    pub fn parse_layered() -> Self {
+        Self::from_matches(&Self::command().get_matches())
+    }
+
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let mut args = Self::from_arg_matches(matches).unwrap_or_else(|e| e.exit());
+        args.resolve_sources(|id| matches.value_source(id));
+        args
+    }
+
+    /// `--output` wins over `P2P_CHAT_TUI`, other flags override their variable in clap.
+    /// `source` tells where the value of the argument with the id came from
+    pub fn resolve_sources(&mut self, source: impl Fn(&str) -> Option<ValueSource>) {
+        if source("tui") == Some(ValueSource::EnvVariable)
+            && source("output") == Some(ValueSource::CommandLine)
+        {
+            self.tui = None;
+        }
+    }
+
+    /// `--mdns` or `--no-mdns`, whichever came last, None if neither was given
+    pub fn mdns(&self) -> Option<bool> {
+        match self.no_mdns {
+            true => Some(false),
+            false => self.mdns,
+        }
+    }
+
+    /// `--tui` or `--no-tui`, whichever came last, None if neither was given
+    pub fn tui(&self) -> Option<bool> {
+        match self.no_tui {
+            true => Some(false),
+            false => self.tui,
+        }
+    }
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    /// One JSON object per line: command results, received messages and other events
+    Json,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Mode {
+    /// Run the node without a front end, controlled with JSON lines over a Unix socket
+    Daemon,
+    /// Publish one message and exit, waiting for peers on the topic first.
+    /// Exits with 0 once sent, 2 if sending failed and 3 if too few peers were found
+    Send {
+        #[arg(long)]
+        topic: String,
+        #[arg(long, required_unless_present = "file")]
+        message: Option<String>,
+        /// File offered with the message, peers download it while the node is online.
+        /// The node waits for the first download, see --linger
+        #[arg(long, value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+        file: Option<PathBuf>,
+        /// Passphrase of a private topic
+        #[arg(long)]
+        key: Option<String>,
+        /// Connected peers subscribed to the topic needed to publish
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        min_peers: usize,
+        /// Seconds to wait for the peers
+        #[arg(long, value_name = "SECS", default_value_t = 30)]
+        wait: u64,
+        /// Seconds to stay online after sending, so slow links get the message. With --file,
+        /// the most to wait for a peer to download it [default: 0, 60 with --file]
+        #[arg(long, value_name = "SECS")]
+        linger: Option<u64>,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Send a command to a running daemon and print the result as JSON
+    Ctl {
+        #[command(subcommand)]
+        action: CtlAction,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CtlAction {
+    /// Join the topic and make it active
+    Subscribe {
+        topic: String,
+        /// Passphrase of a private topic
+        #[arg(long)]
+        key: Option<String>,
+    },
+    /// Leave the topic
+    Unsubscribe { topic: String },
+    /// Publish the text and the file to the topic, the active one by default
+    Send {
+        text: Option<String>,
+        #[arg(long)]
+        topic: Option<String>,
+        #[arg(long, value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+        file: Option<PathBuf>,
+    },
+    /// Send a private message to the nickname, peer id or short id
+    Dm { peer: String, text: String },
+    /// Show connected and discovered peers
+    Peers,
+    /// Print events of the node, one JSON object per line, until it stops
+    Events,
+    /// Stop the daemon
+    Stop,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigAction {
+    /// Print the configuration after the config file, environment and flags are merged
+    Show,
+}
+
+impl From<OutputFormat> for Output {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Text => Output::Text,
+            OutputFormat::Json => Output::Json,
+        }
+    }
+}
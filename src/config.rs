@@ -0,0 +1,426 @@
+use crate::args::{Args, OutputFormat};
+use anyhow::{Context, Result, bail};
+use libp2p::Multiaddr;
+use p2p_chat::models::common::peer_name::validate_nick;
+use p2p_chat::models::common::short_peer_id::DEFAULT_SHORT_ID_LEN;
+use p2p_chat::models::node::chat_node::{DEFAULT_BUFFER_SIZE, DEFAULT_DOWNLOAD_DIR};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const APP_DIR_NAME: &str = "p2p-chat";
+const CONFIG_FILE_NAME: &str = "config.toml";
+const SHORT_ID_LEN_RANGE: RangeInclusive<usize> = 4..=16;
+
+/// Settings of the config file with environment variables and flags applied on top.
+/// Missing keys and sections take the defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub network: NetworkConfig,
+    pub gossipsub: GossipsubConfig,
+    pub discovery: DiscoveryConfig,
+    pub storage: StorageConfig,
+    pub ui: UiConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// listen.txt in the data dir or any interface if empty
+    #[serde(with = "multiaddrs")]
+    pub listen: Vec<Multiaddr>,
+    /// dialed on start along with bootstrap.txt in the data dir
+    #[serde(with = "multiaddrs")]
+    pub peers: Vec<Multiaddr>,
+    /// commands and events queued between the node and the front end
+    pub buffer_size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GossipsubConfig {
+    pub heartbeat_interval_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscoveryConfig {
+    pub mdns: bool,
+}
+
+/// Relative paths of the config file are resolved against its directory, those of flags,
+/// environment variables and the defaults against the working directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub data_dir: PathBuf,
+    pub identity: PathBuf,
+    pub download_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UiConfig {
+    pub tui: bool,
+    pub output: OutputFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nick: Option<String>,
+    pub short_id_len: usize,
+    pub response_timeout_ms: u64,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            listen: Vec::new(),
+            peers: Vec::new(),
+            buffer_size: DEFAULT_BUFFER_SIZE,
+        }
+    }
+}
+
+impl Default for GossipsubConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval_ms: 10_000,
+        }
+    }
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self { mdns: true }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: PathBuf::from("./data"),
+            identity: PathBuf::from("./identity.key"),
+            download_dir: PathBuf::from(DEFAULT_DOWNLOAD_DIR),
+        }
+    }
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            tui: false,
+            output: OutputFormat::default(),
+            nick: None,
+            short_id_len: DEFAULT_SHORT_ID_LEN,
+            response_timeout_ms: 3000,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file and applies the flags, which clap already merged with
+    /// the environment. Only a file given with `--config` has to exist
+    pub fn load(args: &Args) -> Result<Self> {
+        let mut config = match file_path(args) {
+            Some(path) if path.exists() || args.config.is_some() => Self::read(&path)?,
+            _ => Self::default(),
+        };
+
+        config.apply(args);
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn read(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read config file {}", path.display()))?;
+
+        let mut config: Self = toml::from_str(&content)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        if let Some(dir) = path.parent() {
+            config.storage.resolve(&content, dir)?;
+        }
+        Ok(config)
+    }
+
+    fn apply(&mut self, args: &Args) {
+        if !args.listen.is_empty() {
+            self.network.listen = args.listen.clone();
+        }
+        self.network.peers.extend(args.peers.iter().cloned());
+        if let Some(size) = args.buffer_size {
+            self.network.buffer_size = size;
+        }
+
+        if let Some(interval) = args.heartbeat_interval {
+            self.gossipsub.heartbeat_interval_ms = interval;
+        }
+
+        if let Some(mdns) = args.mdns() {
+            self.discovery.mdns = mdns;
+        }
+
+        if let Some(dir) = &args.data_dir {
+            self.storage.data_dir = dir.clone();
+        }
+        if let Some(path) = &args.identity {
+            self.storage.identity = path.clone();
+        }
+        if let Some(dir) = &args.dir {
+            self.storage.download_dir = dir.clone();
+        }
+
+        // the terminal UI only shows text, the layer setting one of them wins
+        if let Some(output) = args.output {
+            self.ui.output = output;
+            self.ui.tui &= output == OutputFormat::Text;
+        }
+        if let Some(tui) = args.tui() {
+            self.ui.tui = tui;
+            if tui {
+                self.ui.output = OutputFormat::Text;
+            }
+        }
+        if let Some(nick) = &args.nick {
+            self.ui.nick = Some(nick.clone());
+        }
+        if let Some(len) = args.short_id_len {
+            self.ui.short_id_len = len;
+        }
+        if let Some(timeout) = args.response_timeout {
+            self.ui.response_timeout_ms = timeout;
+        }
+    }
+
+    /// Checks values the file could set, flags are checked by clap
+    fn validate(&mut self) -> Result<()> {
+        if let Some(nick) = &self.ui.nick {
+            self.ui.nick = Some(validate_nick(nick).context("Invalid ui.nick")?);
+        }
+
+        if !SHORT_ID_LEN_RANGE.contains(&self.ui.short_id_len) {
+            bail!(
+                "ui.short_id_len must be in {}..={}",
+                SHORT_ID_LEN_RANGE.start(),
+                SHORT_ID_LEN_RANGE.end()
+            );
+        }
+
+        if self.network.buffer_size == 0 {
+            bail!("network.buffer_size must be positive");
+        }
+
+        if self.gossipsub.heartbeat_interval_ms == 0 {
+            bail!("gossipsub.heartbeat_interval_ms must be positive");
+        }
+
+        if self.ui.tui && self.ui.output == OutputFormat::Json {
+            bail!("ui.tui can't be used with ui.output = \"json\"");
+        }
+
+        Ok(())
+    }
+
+    pub fn heartbeat_interval(&self) -> Duration {
+        Duration::from_millis(self.gossipsub.heartbeat_interval_ms)
+    }
+
+    pub fn response_timeout(&self) -> Duration {
+        Duration::from_millis(self.ui.response_timeout_ms)
+    }
+
+    /// Effective configuration as TOML, with the file it was read from
+    pub fn show(&self, args: &Args) -> Result<String> {
+        let source = match file_path(args) {
+            Some(path) if path.exists() => format!("# Config file: {}", path.display()),
+            Some(path) => format!("# Config file: {} (not found)", path.display()),
+            None => "# Config file: none".to_string(),
+        };
+
+        Ok(format!("{source}\n\n{}", toml::to_string_pretty(self)?))
+    }
+}
+
+impl StorageConfig {
+    /// Joins relative paths set in the config file `content` to its directory
+    fn resolve(&mut self, content: &str, dir: &Path) -> Result<()> {
+        let file: toml::Table = content.parse()?;
+        let Some(storage) = file.get("storage").and_then(toml::Value::as_table) else {
+            return Ok(());
+        };
+
+        for (key, path) in [
+            ("data_dir", &mut self.data_dir),
+            ("identity", &mut self.identity),
+            ("download_dir", &mut self.download_dir),
+        ] {
+            if storage.contains_key(key) && path.is_relative() {
+                *path = dir.join(&*path);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `--config` or config.toml in `$XDG_CONFIG_HOME/p2p-chat`, `~/.config/p2p-chat` if it isn't set
+fn file_path(args: &Args) -> Option<PathBuf> {
+    if let Some(path) = &args.config {
+        return Some(path.clone());
+    }
+
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_dir.join(APP_DIR_NAME).join(CONFIG_FILE_NAME))
+}
+
+/// Multiaddrs as strings, libp2p is built without serde
+mod multiaddrs {
+    use libp2p::Multiaddr;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(addrs: &[Multiaddr], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(addrs.iter().map(ToString::to_string))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Multiaddr>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|addr| {
+                addr.parse()
+                    .map_err(|e| D::Error::custom(format!("invalid address {addr}: {e}")))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use clap::parser::ValueSource;
+
+    const FILE: &str = r#"
+        [network]
+        peers = ["/ip4/10.0.0.1/tcp/4001"]
+
+        [discovery]
+        mdns = false
+
+        [ui]
+        nick = "alice"
+        output = "json"
+    "#;
+
+    /// Flags without the `P2P_CHAT_*` variables of the environment running the tests
+    fn parse(flags: &[&str]) -> Args {
+        let command = Args::command().mut_args(|arg| arg.env(None));
+        Args::from_matches(&command.get_matches_from([&["p2p-chat"], flags].concat()))
+    }
+
+    fn load(file: &str, flags: &[&str]) -> Result<Config> {
+        let mut config: Config = toml::from_str(file).unwrap();
+        config.apply(&parse(flags));
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[test]
+    fn flags_override_file() {
+        let config = load(
+            FILE,
+            &[
+                "--peer",
+                "/ip4/10.0.0.2/tcp/4001",
+                "--nick",
+                "carol",
+                "--heartbeat-interval",
+                "500",
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(config.network.peers.len(), 2);
+        assert!(!config.discovery.mdns);
+        assert_eq!(config.ui.nick.as_deref(), Some("carol"));
+        assert_eq!(config.ui.output, OutputFormat::Json);
+        assert_eq!(config.heartbeat_interval(), Duration::from_millis(500));
+        assert_eq!(config.storage, StorageConfig::default());
+    }
+
+    #[test]
+    fn booleans_switch_both_ways() {
+        assert!(load(FILE, &["--mdns"]).unwrap().discovery.mdns);
+        assert!(!load("", &["--no-mdns"]).unwrap().discovery.mdns);
+        assert!(!load("", &["--mdns=false"]).unwrap().discovery.mdns);
+        assert!(load("", &["--no-mdns", "--mdns"]).unwrap().discovery.mdns);
+        assert!(!load("", &["--mdns", "--no-mdns"]).unwrap().discovery.mdns);
+
+        assert!(load("", &["--tui"]).unwrap().ui.tui);
+        assert!(!load("[ui]\ntui = true", &["--no-tui"]).unwrap().ui.tui);
+    }
+
+    #[test]
+    fn higher_layer_wins_between_tui_and_json() {
+        let config = load(FILE, &["--tui"]).unwrap();
+        assert!(config.ui.tui);
+        assert_eq!(config.ui.output, OutputFormat::Text);
+
+        let config = load("[ui]\ntui = true", &["--output", "json"]).unwrap();
+        assert!(!config.ui.tui);
+        assert_eq!(config.ui.output, OutputFormat::Json);
+
+        assert!(load("[ui]\ntui = true\noutput = \"json\"", &[]).is_err());
+    }
+
+    #[test]
+    fn output_flag_wins_over_tui_variable() {
+        let mut args = parse(&["--tui", "--output", "json"]);
+        args.resolve_sources(|id| match id {
+            "tui" => Some(ValueSource::EnvVariable),
+            _ => Some(ValueSource::CommandLine),
+        });
+
+        let mut config = Config::default();
+        config.apply(&args);
+        assert!(!config.ui.tui);
+        assert_eq!(config.ui.output, OutputFormat::Json);
+
+        // both given as flags
+        let mut config = Config::default();
+        config.apply(&parse(&["--tui", "--output", "json"]));
+        assert!(config.ui.tui);
+        assert_eq!(config.ui.output, OutputFormat::Text);
+    }
+
+    #[test]
+    fn relative_paths_of_the_file_resolve_against_its_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(
+            &path,
+            "[storage]\ndata_dir = \"data\"\ndownload_dir = \"/srv/downloads\"",
+        )
+        .unwrap();
+
+        let storage = Config::read(&path).unwrap().storage;
+
+        assert_eq!(storage.data_dir, dir.path().join("data"));
+        assert_eq!(storage.download_dir, PathBuf::from("/srv/downloads"));
+        assert_eq!(storage.identity, StorageConfig::default().identity);
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_bad_values() {
+        assert!(toml::from_str::<Config>("[ui]\ncolor = true").is_err());
+        assert!(toml::from_str::<Config>("[network]\npeers = [\"nope\"]").is_err());
+
+        let mut config: Config = toml::from_str("[ui]\nshort_id_len = 2").unwrap();
+        assert!(config.validate().is_err());
+    }
+}
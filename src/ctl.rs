@@ -0,0 +1,57 @@
+use crate::args::CtlAction;
+use p2p_chat::models::common::errors::ControlError;
+use p2p_chat::models::control::client::ControlClient;
+use p2p_chat::models::control::protocol::{Passphrase, Request};
+use std::path::{Path, PathBuf};
+
+/// Sends the action to the daemon and prints its result, returns the exit code
+pub async fn run(socket: &Path, action: CtlAction) -> u8 {
+    match execute(socket, action).await {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{e}");
+            1
+        }
+    }
+}
+
+async fn execute(socket: &Path, action: CtlAction) -> Result<(), ControlError> {
+    let mut client = ControlClient::connect(socket).await?;
+
+    let request = match action {
+        CtlAction::Subscribe { topic, key } => Request::Subscribe {
+            topic,
+            key: key.map(Passphrase),
+        },
+        CtlAction::Unsubscribe { topic } => Request::Unsubscribe { topic },
+        CtlAction::Send { text, topic, file } => Request::Send {
+            topic,
+            text,
+            // the daemon runs in another directory
+            file: file.map(absolute).transpose()?,
+        },
+        CtlAction::Dm { peer, text } => Request::DirectMessage { peer, text },
+        CtlAction::Peers => Request::Peers,
+        CtlAction::Events => Request::Events,
+        CtlAction::Stop => Request::Shutdown,
+    };
+    let streaming = request == Request::Events;
+
+    let result = client.request(request).await?;
+
+    if !result.is_null() {
+        println!("{result}");
+    }
+
+    if streaming {
+        while let Some(event) = client.next_event().await? {
+            println!("{event}");
+        }
+    }
+
+    Ok(())
+}
+
+fn absolute(path: PathBuf) -> Result<PathBuf, ControlError> {
+    std::fs::canonicalize(&path).map_err(|source| ControlError::FileNotFound { path, source })
+}
@@ -0,0 +1,6 @@
+pub mod models;
+pub mod traits;
+
+pub use models::common::notification::Notification;
+pub use models::node::chat_node::{ChatNode, RunningNode};
+pub use models::node::handle::{NodeEvents, NodeHandle};
@@ -1,14 +1,43 @@
-use crate::models::app::App;
+use crate::app::App;
+use crate::args::{Args, ConfigAction, Mode};
+use crate::config::Config;
 use anyhow::Result;
 
-mod models;
-mod traits;
+mod app;
+mod args;
+mod config;
+#[cfg(unix)]
+mod ctl;
+mod one_shot;
+
+/// Socket of the daemon in the data dir when `--socket` isn't given
+const SOCKET_FILE_NAME: &str = "control.sock";
 
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
 
-    App::new()?.run().await;
+    let args = Args::parse_layered();
+    let config = Config::load(&args)?;
+    let socket = args
+        .socket
+        .clone()
+        .unwrap_or_else(|| config.storage.data_dir.join(SOCKET_FILE_NAME));
+
+    let code = match args.mode.clone() {
+        Some(Mode::Config {
+            action: ConfigAction::Show,
+        }) => {
+            print!("{}", config.show(&args)?);
+            0
+        }
+        #[cfg(unix)]
+        Some(Mode::Ctl { action }) => ctl::run(&socket, action).await,
+        #[cfg(not(unix))]
+        Some(Mode::Ctl { .. }) => anyhow::bail!("Control API needs Unix domain sockets"),
+        mode => App::new(config, mode, args.passphrase, socket)?.run().await,
+    };
 
-    Ok(())
+    // the line editor thread may still be blocked on stdin, so the process doesn't wait for it
+    std::process::exit(code.into())
 }
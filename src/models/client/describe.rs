@@ -0,0 +1,260 @@
+use crate::models::common::errors::{
+    DirectMessageError, HistoryError, NickError, SendingError, TopicError, TransferError,
+};
+use crate::models::common::file::File;
+use crate::models::common::response::{
+    AddressList, ConnectOutcome, DirectMessageOutcome, Direction, NickOutcome, PeerInfo,
+    SendOutcome, SubscribeOutcome, SwitchOutcome, UnsubscribeOutcome,
+};
+use crate::models::history::history_store::HistoryRecord;
+use libp2p::Multiaddr;
+use libp2p::gossipsub::PublishError;
+
+/// Wording of command results shown to the user, one entry per line
+pub trait Describe {
+    fn describe(&self) -> Vec<String>;
+
+    /// Shown as failed, e.g. a message that wasn't sent
+    fn failed(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Describe, E: Describe> Describe for Result<T, E> {
+    fn describe(&self) -> Vec<String> {
+        match self {
+            Ok(outcome) => outcome.describe(),
+            Err(e) => e.describe(),
+        }
+    }
+
+    fn failed(&self) -> bool {
+        self.is_err()
+    }
+}
+
+impl Describe for SendOutcome {
+    fn describe(&self) -> Vec<String> {
+        vec![format!(
+            "Sent to [{}], {} peers subscribed",
+            self.topic, self.recipients
+        )]
+    }
+}
+
+impl Describe for SubscribeOutcome {
+    fn describe(&self) -> Vec<String> {
+        let line = match self {
+            SubscribeOutcome::Subscribed => "You have subscribed to the topic successfully",
+            SubscribeOutcome::SubscribedPrivate => {
+                "You have subscribed to the private topic successfully"
+            }
+            SubscribeOutcome::AlreadySubscribed => "You are already subscribed to this topic",
+        };
+
+        vec![line.to_string()]
+    }
+}
+
+impl Describe for UnsubscribeOutcome {
+    fn describe(&self) -> Vec<String> {
+        let line = match &self.active {
+            Some(active) => format!(
+                "You have unsubscribed from the topic successfully, active topic is {active}"
+            ),
+            None => "You have unsubscribed from the topic successfully".to_string(),
+        };
+
+        vec![line]
+    }
+}
+
+impl Describe for SwitchOutcome {
+    fn describe(&self) -> Vec<String> {
+        vec![format!("Active topic is {} now", self.active)]
+    }
+}
+
+impl Describe for DirectMessageOutcome {
+    fn describe(&self) -> Vec<String> {
+        vec![format!("Delivered to {}", self.recipient)]
+    }
+}
+
+impl Describe for ConnectOutcome {
+    fn describe(&self) -> Vec<String> {
+        let line = match self {
+            ConnectOutcome::Dialing(addr) => format!("Dialing {addr}..."),
+            ConnectOutcome::AlreadyKnown(addr) => format!("{addr} is already in the peer list"),
+        };
+
+        vec![line]
+    }
+}
+
+impl Describe for NickOutcome {
+    fn describe(&self) -> Vec<String> {
+        let line = match self {
+            NickOutcome::Current(Some(nick)) => format!("Your nickname is {nick}"),
+            NickOutcome::Current(None) => {
+                "You have no nickname, set it with /nick <name>".to_string()
+            }
+            NickOutcome::Changed(nick) => format!("Your nickname is {nick} now"),
+        };
+
+        vec![line]
+    }
+}
+
+impl Describe for AddressList {
+    fn describe(&self) -> Vec<String> {
+        let mut lines = vec!["Listening on:".to_string()];
+        lines.extend(addr_lines(&self.listen));
+        lines.push("Observed by peers:".to_string());
+        lines.extend(addr_lines(&self.observed));
+        lines
+    }
+}
+
+impl Describe for Vec<PeerInfo> {
+    fn describe(&self) -> Vec<String> {
+        if self.is_empty() {
+            return vec!["No peers found".to_string()];
+        }
+
+        let mut lines = Vec::new();
+
+        for peer in self {
+            let mut line = match peer.connection {
+                Some(Direction::Inbound) => format!("{}: connected, inbound", peer.name),
+                Some(Direction::Outbound) => format!("{}: connected, outbound", peer.name),
+                None => format!("{}: discovered", peer.name),
+            };
+
+            if let Some(rtt) = peer.rtt {
+                line.push_str(&format!(", {} ms", rtt.as_millis()));
+            }
+
+            if !peer.topics.is_empty() {
+                line.push_str(&format!(", topics: {}", peer.topics.join(", ")));
+            }
+
+            lines.push(line);
+            lines.extend(peer.addresses.iter().map(|addr| format!("  {addr}")));
+        }
+
+        lines
+    }
+}
+
+impl Describe for Vec<HistoryRecord> {
+    fn describe(&self) -> Vec<String> {
+        if self.is_empty() {
+            return vec!["No messages found".to_string()];
+        }
+
+        self.iter().map(ToString::to_string).collect()
+    }
+}
+
+impl Describe for File {
+    fn describe(&self) -> Vec<String> {
+        vec![format!("Downloading {self}...")]
+    }
+}
+
+impl Describe for SendingError {
+    fn describe(&self) -> Vec<String> {
+        let line = match self {
+            SendingError::NoSubscribedTopic => "You are not subscribed to any topic".to_string(),
+            SendingError::NotSubscribedTo(topic) => {
+                format!("You are not subscribed to the topic {topic}")
+            }
+            SendingError::CantEncodeMessage(_) => "Something wrong with the message".to_string(),
+            SendingError::CantEncryptMessage(_) => {
+                "Something went wrong while encrypting message".to_string()
+            }
+            SendingError::Other(PublishError::InsufficientPeers) => {
+                "No peers are subscribed to the topic yet".to_string()
+            }
+            SendingError::Other(_) => "Something went wrong while sending message".to_string(),
+        };
+
+        vec![line]
+    }
+}
+
+impl Describe for TopicError {
+    fn describe(&self) -> Vec<String> {
+        let line = match self {
+            TopicError::NotSubscribed(topic) => {
+                format!("You are not subscribed to the topic {topic}")
+            }
+            TopicError::CantSubscribe(_) => "Something went wrong".to_string(),
+        };
+
+        vec![line]
+    }
+}
+
+impl Describe for HistoryError {
+    fn describe(&self) -> Vec<String> {
+        vec!["Something went wrong while reading history".to_string()]
+    }
+}
+
+impl Describe for DirectMessageError {
+    fn describe(&self) -> Vec<String> {
+        vec![self.to_string()]
+    }
+}
+
+impl Describe for TransferError {
+    fn describe(&self) -> Vec<String> {
+        vec![self.to_string()]
+    }
+}
+
+impl Describe for NickError {
+    fn describe(&self) -> Vec<String> {
+        vec![self.to_string()]
+    }
+}
+
+fn addr_lines(addrs: &[Multiaddr]) -> Vec<String> {
+    match addrs.is_empty() {
+        true => vec!["  none".to_string()],
+        false => addrs.iter().map(|addr| format!("  {addr}")).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_are_described_by_variant() {
+        let sent: Result<SendOutcome, SendingError> = Ok(SendOutcome {
+            message_id: "1".to_string(),
+            topic: "ops".to_string(),
+            recipients: 2,
+        });
+        assert_eq!(sent.describe(), vec!["Sent to [ops], 2 peers subscribed"]);
+
+        let failed: Result<SendOutcome, SendingError> =
+            Err(SendingError::NotSubscribedTo("ops".to_string()));
+        assert_eq!(
+            failed.describe(),
+            vec!["You are not subscribed to the topic ops"]
+        );
+    }
+
+    #[test]
+    fn empty_lists() {
+        assert_eq!(Vec::<PeerInfo>::new().describe(), vec!["No peers found"]);
+        assert_eq!(
+            AddressList::default().describe(),
+            vec!["Listening on:", "  none", "Observed by peers:", "  none"]
+        );
+    }
+}
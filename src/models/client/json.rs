@@ -0,0 +1,546 @@
+use crate::models::client::describe::Describe;
+use crate::models::common::file::File;
+use crate::models::common::message::{Message, ReceivedMessage};
+use crate::models::common::notification::Notification;
+use crate::models::common::response::{
+    AddressList, ConnectOutcome, DirectMessageOutcome, Direction, NickOutcome, PeerInfo,
+    SendOutcome, SubscribeOutcome, SwitchOutcome, UnsubscribeOutcome,
+};
+use crate::models::history::history_store::HistoryRecord;
+use jiff::Timestamp;
+use libp2p::Multiaddr;
+use serde_json::{Value, json};
+
+/// Command results and events as JSON objects, for the control API and the JSON output
+pub trait ToJson {
+    fn to_json(&self) -> Value;
+}
+
+/// Error is its wording, whether it failed is told by `Describe::failed`
+impl<T: ToJson, E: Describe> ToJson for Result<T, E> {
+    fn to_json(&self) -> Value {
+        match self {
+            Ok(outcome) => outcome.to_json(),
+            Err(e) => json!(e.describe().join(" ")),
+        }
+    }
+}
+
+impl ToJson for SendOutcome {
+    fn to_json(&self) -> Value {
+        json!({
+            "message_id": self.message_id,
+            "topic": self.topic,
+            "recipients": self.recipients,
+        })
+    }
+}
+
+impl ToJson for SubscribeOutcome {
+    fn to_json(&self) -> Value {
+        let status = match self {
+            SubscribeOutcome::Subscribed => "subscribed",
+            SubscribeOutcome::SubscribedPrivate => "subscribed_private",
+            SubscribeOutcome::AlreadySubscribed => "already_subscribed",
+        };
+
+        json!({ "status": status })
+    }
+}
+
+impl ToJson for UnsubscribeOutcome {
+    fn to_json(&self) -> Value {
+        json!({ "active": self.active })
+    }
+}
+
+impl ToJson for SwitchOutcome {
+    fn to_json(&self) -> Value {
+        json!({ "active": self.active })
+    }
+}
+
+impl ToJson for DirectMessageOutcome {
+    fn to_json(&self) -> Value {
+        json!({
+            "recipient": self.recipient.to_string(),
+            "peer_id": self.peer_id.to_string(),
+        })
+    }
+}
+
+impl ToJson for ConnectOutcome {
+    fn to_json(&self) -> Value {
+        match self {
+            ConnectOutcome::Dialing(addr) => {
+                json!({ "status": "dialing", "address": addr.to_string() })
+            }
+            ConnectOutcome::AlreadyKnown(addr) => {
+                json!({ "status": "already_known", "address": addr.to_string() })
+            }
+        }
+    }
+}
+
+impl ToJson for NickOutcome {
+    fn to_json(&self) -> Value {
+        match self {
+            NickOutcome::Current(nick) => json!({ "nick": nick, "changed": false }),
+            NickOutcome::Changed(nick) => json!({ "nick": nick, "changed": true }),
+        }
+    }
+}
+
+impl ToJson for AddressList {
+    fn to_json(&self) -> Value {
+        json!({
+            "listen": addr_strings(&self.listen),
+            "observed": addr_strings(&self.observed),
+        })
+    }
+}
+
+impl ToJson for Vec<HistoryRecord> {
+    fn to_json(&self) -> Value {
+        json!(self)
+    }
+}
+
+impl ToJson for Vec<PeerInfo> {
+    fn to_json(&self) -> Value {
+        self.iter()
+            .map(|peer| {
+                let connection = peer.connection.map(|direction| match direction {
+                    Direction::Inbound => "inbound",
+                    Direction::Outbound => "outbound",
+                });
+
+                json!({
+                    "name": peer.name.to_string(),
+                    "peer_id": peer.peer_id.to_string(),
+                    "nick": peer.name.nick(),
+                    "connection": connection,
+                    "rtt_ms": peer.rtt.map(|rtt| rtt.as_millis() as u64),
+                    "topics": peer.topics,
+                    "addresses": addr_strings(&peer.addresses),
+                })
+            })
+            .collect()
+    }
+}
+
+impl ToJson for File {
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "size": self.size,
+            "hash": self.hash,
+        })
+    }
+}
+
+/// Every event has a `timestamp`, the time it was received for messages
+/// and the time it was reported for the others
+impl ToJson for Notification {
+    fn to_json(&self) -> Value {
+        let mut event = event_json(self);
+
+        if event.get("timestamp").is_none() {
+            event["timestamp"] = json!(Timestamp::now().as_second());
+        }
+
+        event
+    }
+}
+
+fn event_json(notification: &Notification) -> Value {
+    match notification {
+        Notification::Message(ReceivedMessage {
+            id,
+            topic,
+            sender,
+            sender_id,
+            timestamp,
+            msg,
+        }) => {
+            let mut event = message_json(msg);
+            event["event"] = json!("message");
+            event["message_id"] = json!(id);
+            event["topic"] = json!(topic);
+            event["sender"] = json!(sender.to_string());
+            event["sender_id"] = json!(sender_id.to_string());
+            event["timestamp"] = json!(timestamp);
+            event
+        }
+        Notification::SyncedHistory { topic, records } => json!({
+            "event": "synced_history",
+            "topic": topic,
+            "messages": records,
+        }),
+        Notification::UndecryptableMessage {
+            topic,
+            sender,
+            sender_id,
+            timestamp,
+        } => json!({
+            "event": "undecryptable_message",
+            "topic": topic,
+            "sender": sender.to_string(),
+            "sender_id": sender_id.to_string(),
+            "timestamp": timestamp,
+        }),
+        Notification::DirectMessage {
+            id,
+            sender,
+            sender_id,
+            timestamp,
+            msg,
+        } => {
+            let mut event = message_json(msg);
+            event["event"] = json!("direct_message");
+            event["message_id"] = json!(id);
+            event["sender"] = json!(sender.to_string());
+            event["sender_id"] = json!(sender_id.to_string());
+            event["timestamp"] = json!(timestamp);
+            event
+        }
+        Notification::PeerJoined { name, peer_id } => json!({
+            "event": "peer_joined",
+            "peer": name.to_string(),
+            "peer_id": peer_id.to_string(),
+        }),
+        Notification::PeerLeft { name, peer_id } => json!({
+            "event": "peer_left",
+            "peer": name.to_string(),
+            "peer_id": peer_id.to_string(),
+        }),
+        Notification::NickChanged {
+            peer_id,
+            old,
+            new,
+            conflicts,
+        } => json!({
+            "event": "nick_changed",
+            "peer_id": peer_id.to_string(),
+            "old": old.to_string(),
+            "new": new.to_string(),
+            "conflicts": conflicts
+                .iter()
+                .map(|(name, peer_id)| json!({
+                    "peer": name.to_string(),
+                    "peer_id": peer_id.to_string(),
+                }))
+                .collect::<Vec<_>>(),
+        }),
+        Notification::DownloadProgress {
+            name,
+            received,
+            size,
+        } => json!({
+            "event": "download_progress",
+            "name": name,
+            "received": received,
+            "size": size,
+        }),
+        Notification::DownloadFinished { name, path, hash } => json!({
+            "event": "download_finished",
+            "name": name,
+            "path": path,
+            "hash": hash,
+        }),
+        Notification::DownloadFailed { name, reason } => json!({
+            "event": "download_failed",
+            "name": name,
+            "reason": reason,
+        }),
+        Notification::UploadFinished {
+            name,
+            peer,
+            peer_id,
+        } => json!({
+            "event": "upload_finished",
+            "name": name,
+            "peer": peer.to_string(),
+            "peer_id": peer_id.to_string(),
+        }),
+    }
+}
+
+fn addr_strings(addrs: &[Multiaddr]) -> Vec<String> {
+    addrs.iter().map(ToString::to_string).collect()
+}
+
+fn message_json(msg: &Message) -> Value {
+    json!({
+        "text": msg.data,
+        "file": msg.file.as_ref().map(ToJson::to_json),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::common::errors::TopicError;
+    use crate::models::common::peer_name::PeerName;
+    use libp2p::PeerId;
+    use std::time::Duration;
+
+    #[test]
+    fn message_event() {
+        let sender_id = PeerId::random();
+        let notification = Notification::Message(ReceivedMessage {
+            id: "3132".to_string(),
+            topic: "ops".to_string(),
+            sender: PeerName::new("x7Fq9a".parse().unwrap(), Some("alice")),
+            sender_id,
+            timestamp: 1_700_000_000,
+            msg: Message {
+                data: Some("hello".to_string()),
+                file: None,
+            },
+        });
+
+        assert_eq!(
+            notification.to_json(),
+            json!({
+                "event": "message",
+                "message_id": "3132",
+                "topic": "ops",
+                "sender": "alice (…x7Fq9a)",
+                "sender_id": sender_id.to_string(),
+                "timestamp": 1_700_000_000,
+                "text": "hello",
+                "file": null,
+            })
+        );
+    }
+
+    fn alice() -> PeerName {
+        PeerName::new("x7Fq9a".parse().unwrap(), Some("alice"))
+    }
+
+    /// Event without its timestamp, which is the time it was reported for most events
+    fn reported(notification: Notification) -> Value {
+        let mut event = notification.to_json();
+        let timestamp = event["timestamp"].as_i64().unwrap();
+        assert!((timestamp - Timestamp::now().as_second()).abs() <= 1);
+
+        event.as_object_mut().unwrap().remove("timestamp");
+        event
+    }
+
+    #[test]
+    fn direct_and_undecryptable_message_events() {
+        let sender_id = PeerId::random();
+
+        let direct = Notification::DirectMessage {
+            id: "77".to_string(),
+            sender: alice(),
+            sender_id,
+            timestamp: 1_700_000_000,
+            msg: Message {
+                data: Some("psst".to_string()),
+                file: None,
+            },
+        };
+        assert_eq!(
+            direct.to_json(),
+            json!({
+                "event": "direct_message",
+                "message_id": "77",
+                "sender": "alice (…x7Fq9a)",
+                "sender_id": sender_id.to_string(),
+                "timestamp": 1_700_000_000,
+                "text": "psst",
+                "file": null,
+            })
+        );
+
+        let undecryptable = Notification::UndecryptableMessage {
+            topic: "ops".to_string(),
+            sender: alice(),
+            sender_id,
+            timestamp: 1_700_000_000,
+        };
+        assert_eq!(
+            undecryptable.to_json(),
+            json!({
+                "event": "undecryptable_message",
+                "topic": "ops",
+                "sender": "alice (…x7Fq9a)",
+                "sender_id": sender_id.to_string(),
+                "timestamp": 1_700_000_000,
+            })
+        );
+    }
+
+    #[test]
+    fn synced_history_event() {
+        let record = HistoryRecord {
+            message_id: "1".to_string(),
+            topic: "ops".to_string(),
+            sender: "12D3KooW".to_string(),
+            timestamp: 1_700_000_000,
+            text: Some("hi".to_string()),
+            file_name: None,
+            verified: false,
+            private: false,
+        };
+        let notification = Notification::SyncedHistory {
+            topic: "ops".to_string(),
+            records: vec![record.clone()],
+        };
+
+        assert_eq!(
+            reported(notification),
+            json!({
+                "event": "synced_history",
+                "topic": "ops",
+                "messages": [record],
+            })
+        );
+    }
+
+    #[test]
+    fn peer_events() {
+        let peer_id = PeerId::random();
+        let other_alice = PeerName::new("Zq81cD".parse().unwrap(), Some("alice"));
+        let other_id = PeerId::random();
+
+        assert_eq!(
+            reported(Notification::PeerJoined {
+                name: alice(),
+                peer_id
+            }),
+            json!({
+                "event": "peer_joined",
+                "peer": "alice (…x7Fq9a)",
+                "peer_id": peer_id.to_string(),
+            })
+        );
+        assert_eq!(
+            reported(Notification::PeerLeft {
+                name: alice(),
+                peer_id
+            }),
+            json!({
+                "event": "peer_left",
+                "peer": "alice (…x7Fq9a)",
+                "peer_id": peer_id.to_string(),
+            })
+        );
+        assert_eq!(
+            reported(Notification::NickChanged {
+                peer_id,
+                old: PeerName::new("x7Fq9a".parse().unwrap(), None),
+                new: alice(),
+                conflicts: vec![(other_alice, other_id)],
+            }),
+            json!({
+                "event": "nick_changed",
+                "peer_id": peer_id.to_string(),
+                "old": "…x7Fq9a",
+                "new": "alice (…x7Fq9a)",
+                "conflicts": [{ "peer": "alice (…Zq81cD)", "peer_id": other_id.to_string() }],
+            })
+        );
+    }
+
+    #[test]
+    fn download_events() {
+        let peer_id = PeerId::random();
+        let events = [
+            (
+                Notification::DownloadProgress {
+                    name: "a.txt".to_string(),
+                    received: 5,
+                    size: 10,
+                },
+                json!({ "event": "download_progress", "name": "a.txt", "received": 5, "size": 10 }),
+            ),
+            (
+                Notification::DownloadFinished {
+                    name: "a.txt".to_string(),
+                    path: "downloads/a.txt".into(),
+                    hash: "ab".repeat(32),
+                },
+                json!({
+                    "event": "download_finished",
+                    "name": "a.txt",
+                    "path": "downloads/a.txt",
+                    "hash": "ab".repeat(32),
+                }),
+            ),
+            (
+                Notification::DownloadFailed {
+                    name: "a.txt".to_string(),
+                    reason: "gone".to_string(),
+                },
+                json!({ "event": "download_failed", "name": "a.txt", "reason": "gone" }),
+            ),
+            (
+                Notification::UploadFinished {
+                    name: "a.txt".to_string(),
+                    peer: alice(),
+                    peer_id,
+                },
+                json!({
+                    "event": "upload_finished",
+                    "name": "a.txt",
+                    "peer": "alice (…x7Fq9a)",
+                    "peer_id": peer_id.to_string(),
+                }),
+            ),
+        ];
+
+        for (notification, expected) in events {
+            assert_eq!(reported(notification), expected);
+        }
+    }
+
+    #[test]
+    fn results_carry_peer_ids() {
+        let peer_id = PeerId::random();
+
+        let outcome = DirectMessageOutcome {
+            recipient: alice(),
+            peer_id,
+        };
+        assert_eq!(
+            outcome.to_json(),
+            json!({ "recipient": "alice (…x7Fq9a)", "peer_id": peer_id.to_string() })
+        );
+
+        let peers = vec![PeerInfo {
+            name: alice(),
+            peer_id,
+            connection: Some(Direction::Outbound),
+            rtt: Some(Duration::from_millis(12)),
+            topics: vec!["ops".to_string()],
+            addresses: Vec::new(),
+        }];
+        assert_eq!(
+            peers.to_json(),
+            json!([{
+                "name": "alice (…x7Fq9a)",
+                "peer_id": peer_id.to_string(),
+                "nick": "alice",
+                "connection": "outbound",
+                "rtt_ms": 12,
+                "topics": ["ops"],
+                "addresses": [],
+            }])
+        );
+    }
+
+    #[test]
+    fn error_is_described() {
+        let result: Result<SwitchOutcome, TopicError> =
+            Err(TopicError::NotSubscribed("ops".to_string()));
+
+        assert_eq!(
+            result.to_json(),
+            json!("You are not subscribed to the topic ops")
+        );
+    }
+}
@@ -0,0 +1,703 @@
+pub mod describe;
+pub mod json;
+pub mod signals;
+pub mod tracker;
+
+use crate::models::client::describe::Describe;
+use crate::models::client::json::ToJson;
+use crate::models::client::signals::Signals;
+use crate::models::client::tracker::{CommandId, CommandTracker, Response, Tracked};
+use crate::models::command_line::parser::{self, IdentityCommand, KeySource, UserCommand};
+use crate::models::common::command::Command;
+use crate::models::common::errors::CommandLineError;
+use crate::models::common::file::File;
+use crate::models::common::message::{Message, ReceivedMessage};
+use crate::models::common::notification::Notification;
+use crate::models::common::topic_key::TopicKey;
+use crate::models::identity::identity_manager::IdentityManager;
+use crate::models::node::handle::{NodeEvents, NodeHandle};
+use crate::models::repl::line_editor::Repl;
+use crate::models::tui::terminal_ui::TerminalUi;
+use libp2p::Multiaddr;
+use serde_json::{Value, json};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+const DEFAULT_HISTORY_LIMIT: usize = 20;
+/// How long SwarmManager is given to leave topics and close connections
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Client {
+    session: Session,
+    events: NodeEvents,
+    /// full-screen UI instead of plain lines
+    tui: bool,
+    input_history: Option<PathBuf>,
+}
+
+/// Why the front end stopped, decides the exit code of the process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// quit command or end of input
+    Quit,
+    /// SIGINT or Ctrl-C
+    Interrupted,
+    /// SIGTERM
+    Terminated,
+    /// front end failed
+    Failed,
+    /// one-shot send found too few peers in time
+    NoPeers,
+}
+
+/// User side state shared by the line and the terminal UI:
+/// file attached to the next message, identity and the handle of the node
+pub struct Session {
+    node: NodeHandle,
+    current_file: Option<File>,
+    identity: IdentityManager,
+    tracker: CommandTracker,
+    output: Output,
+}
+
+/// How the line mode prints results and events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Output {
+    #[default]
+    Text,
+    /// one JSON object per line, its `type` is result, pending, event, info or error
+    Json,
+}
+
+impl Exit {
+    pub fn code(self) -> u8 {
+        match self {
+            Exit::Quit => 0,
+            Exit::Failed => 2,
+            Exit::NoPeers => 3,
+            // 128 + signal number, like a shell reports a process stopped by the signal
+            Exit::Interrupted => 130,
+            Exit::Terminated => 143,
+        }
+    }
+}
+
+impl Client {
+    pub fn new(node: NodeHandle, events: NodeEvents, identity: IdentityManager, tui: bool) -> Self {
+        let session = Session {
+            node,
+            current_file: None,
+            identity,
+            tracker: CommandTracker::default(),
+            output: Output::default(),
+        };
+
+        Self {
+            session,
+            events,
+            tui,
+            input_history: None,
+        }
+    }
+
+    /// How long the client waits for SwarmManager before it shows the command as pending
+    pub fn with_response_timeout(mut self, timeout: Duration) -> Self {
+        self.session.tracker = CommandTracker::new(timeout);
+        self
+    }
+
+    /// Format of the line mode output, the terminal UI always shows text
+    pub fn with_output(mut self, output: Output) -> Self {
+        self.session.output = output;
+        self
+    }
+
+    /// File the line mode keeps entered commands in
+    pub fn with_input_history(mut self, path: PathBuf) -> Self {
+        self.input_history = Some(path);
+        self
+    }
+
+    /// Runs the front end until the user quits or a signal comes, then shuts down the swarm
+    pub async fn run(self) -> Exit {
+        log::info!("Running client...");
+
+        let node = self.session.node.clone();
+
+        let exit = match Signals::new() {
+            Ok(signals) if self.tui => TerminalUi::new(self.session, self.events)
+                .run(signals)
+                .await
+                .unwrap_or_else(|e| {
+                    log::error!("Terminal UI failed: {e}");
+                    Exit::Failed
+                }),
+            Ok(signals) => Repl::new(self.session, self.events, self.input_history)
+                .run(signals)
+                .await
+                .unwrap_or_else(|e| {
+                    log::error!("Line editor failed: {e}");
+                    Exit::Failed
+                }),
+            Err(e) => {
+                log::error!("Couldn't listen for signals: {e}");
+                Exit::Failed
+            }
+        };
+
+        shutdown(&node).await;
+        exit
+    }
+}
+
+impl Session {
+    /// Executes the command and returns the lines to show right away.
+    /// Responses of SwarmManager come later from `response`
+    pub async fn execute(&mut self, cmd: UserCommand) -> Vec<String> {
+        match cmd {
+            UserCommand::Subscribe(topic, key_source) => {
+                match self.subscribe(topic, key_source).await {
+                    Ok(_) => Vec::new(),
+                    Err(lines) => self.output.error(lines),
+                }
+            }
+            UserCommand::Unsubscribe(topic) => {
+                self.unsubscribe(topic).await;
+                Vec::new()
+            }
+            UserCommand::Msg(topic, text) => {
+                self.publish(topic, text).await;
+                Vec::new()
+            }
+            UserCommand::DirectMessage(peer, message) => {
+                let msg = Message::build(Some(message), None).await;
+
+                let label = format!("/dm {peer}");
+                self.request(label, Command::new_direct_message(peer, msg))
+                    .await
+            }
+            UserCommand::History(topic, limit) => {
+                let label = format!("/history {topic}");
+                let limit = limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+
+                self.request(label, Command::new_history(topic, limit))
+                    .await
+            }
+            UserCommand::Search(text) => {
+                let label = format!("/search {text}");
+                self.request(label, Command::new_search(text)).await
+            }
+            UserCommand::Download(file_id) => {
+                let label = format!("/download {file_id}");
+                let dir = self.node.download_dir().to_path_buf();
+
+                self.request(label, Command::new_download(file_id, dir))
+                    .await
+            }
+            UserCommand::Connect(addr) => match addr.parse::<Multiaddr>() {
+                Ok(addr) => {
+                    let label = format!("/connect {addr}");
+                    self.request(label, Command::new_connect(addr)).await
+                }
+                Err(e) => self
+                    .output
+                    .error(vec![format!("> Invalid address {addr}: {e}")]),
+            },
+            UserCommand::Nick(nick) => {
+                self.request("/nick".to_string(), Command::new_nick(nick))
+                    .await
+            }
+            UserCommand::Peers => {
+                self.request("/peers".to_string(), Command::new_peers())
+                    .await
+            }
+            UserCommand::Addrs => {
+                self.request("/addrs".to_string(), Command::new_addrs())
+                    .await
+            }
+            UserCommand::Switch(topic) => {
+                self.switch(topic).await;
+                Vec::new()
+            }
+            UserCommand::File(path) => match File::from(path).await {
+                Ok(f) => {
+                    log::info!("File read successfully");
+                    let line = format!("> File loaded successfully, sha256: {}", f.hash);
+                    self.current_file = Some(f);
+                    self.output.info(vec![line])
+                }
+                Err(e) => {
+                    log::error!("Error while reading file: {e}");
+                    self.current_file = None;
+                    self.output.error(vec![format!(
+                        "> Something went wrong while loading file: {e}"
+                    )])
+                }
+            },
+            UserCommand::Identity(cmd) => self
+                .output
+                .info(handle_identity_command(&mut self.identity, cmd)),
+            UserCommand::Help(None) => self.output.info(available_commands()),
+            UserCommand::Help(Some(name)) => match parser::find(&name) {
+                Some(spec) => self.output.info(spec.help()),
+                None => self
+                    .output
+                    .error(vec![format!("> Unknown command /{name}")]),
+            },
+            // front ends stop on their own, there is nothing to execute
+            UserCommand::Quit => Vec::new(),
+        }
+    }
+
+    /// Subscribes the topic with the key derived from `key_source` if given.
+    /// Returns the lines to show if the key couldn't be loaded
+    pub async fn subscribe(
+        &mut self,
+        topic: String,
+        key_source: Option<KeySource>,
+    ) -> Result<CommandId, Vec<String>> {
+        let key = match key_source {
+            Some(source) => match load_topic_key(&topic, source).await {
+                Ok(key) => Some(key),
+                Err(e) => {
+                    log::error!("Error while loading topic key: {e}");
+                    return Err(vec![format!("> Couldn't load topic key: {e}")]);
+                }
+            },
+            None => None,
+        };
+
+        let label = format!("/join {topic}");
+        Ok(self.send(label, Command::new_subscribe(topic, key)).await)
+    }
+
+    pub async fn unsubscribe(&mut self, topic: String) -> CommandId {
+        let label = format!("/part {topic}");
+        self.send(label, Command::new_unsubscribe(topic)).await
+    }
+
+    pub async fn switch(&mut self, topic: String) -> CommandId {
+        let label = format!("/switch {topic}");
+        self.send(label, Command::new_switch(topic)).await
+    }
+
+    /// Publishes the message with the attached file
+    pub async fn publish(&mut self, topic: Option<String>, text: String) -> CommandId {
+        let label = message_label(&text);
+        let msg = Message::build(Some(text), self.current_file.take()).await;
+
+        self.send(label, Command::new_send_message(topic, msg))
+            .await
+    }
+
+    pub fn output(&self) -> Output {
+        self.output
+    }
+
+    /// Next response of a sent command, or the notice that it is pending
+    pub async fn response(&mut self) -> Option<Tracked> {
+        self.tracker.next().await
+    }
+
+    /// Sends the command to SwarmManager, its response comes from `response`
+    async fn request<T>(
+        &mut self,
+        label: String,
+        command: (Command, oneshot::Receiver<T>),
+    ) -> Vec<String>
+    where
+        T: Describe + ToJson + Send + 'static,
+    {
+        self.send(label, command).await;
+        Vec::new()
+    }
+
+    async fn send<T>(
+        &mut self,
+        label: String,
+        (command, response_receiver): (Command, oneshot::Receiver<T>),
+    ) -> CommandId
+    where
+        T: Describe + ToJson + Send + 'static,
+    {
+        let response_receiver = match self.node.send((command, response_receiver)).await {
+            Ok(response_receiver) => response_receiver,
+            Err(e) => {
+                log::error!("Error while sending command to swarm_manager: {e}");
+                // the closed channel is reported as no response
+                oneshot::channel::<T>().1
+            }
+        };
+
+        self.tracker.track(label, response_receiver)
+    }
+}
+
+impl Output {
+    /// Result of a command the client didn't send through the tracker
+    pub fn result<T: Describe + ToJson>(self, label: &str, ans: &T) -> Vec<String> {
+        match self {
+            Output::Text => describe(ans),
+            Output::Json => vec![result_json(label, None, !ans.failed(), ans.to_json())],
+        }
+    }
+
+    /// Response of a sent command, late ones come with the id shown when it was pending
+    pub fn tracked(self, tracked: &Tracked) -> Vec<String> {
+        match (self, tracked) {
+            (Output::Text, Tracked::Pending { id, command }) => vec![format!(
+                "> {command} is pending as #{id}, the response will be shown when it comes"
+            )],
+            (Output::Json, Tracked::Pending { id, command }) => {
+                vec![json!({ "type": "pending", "command": command, "id": id }).to_string()]
+            }
+            (Output::Text, Tracked::Response(response)) => format_response(response),
+            (Output::Json, Tracked::Response(response)) => vec![result_json(
+                &response.command,
+                response.late.then_some(response.id),
+                !response.failed,
+                response.result.clone(),
+            )],
+        }
+    }
+
+    pub fn notification(self, notification: Notification) -> Vec<String> {
+        match self {
+            Output::Text => format_notification(notification),
+            Output::Json => {
+                let mut event = notification.to_json();
+                event["type"] = json!("event");
+                vec![event.to_string()]
+            }
+        }
+    }
+
+    /// Lines shown by the client itself, e.g. help
+    pub fn info(self, lines: Vec<String>) -> Vec<String> {
+        self.text_lines("info", lines)
+    }
+
+    pub fn error(self, lines: Vec<String>) -> Vec<String> {
+        self.text_lines("error", lines)
+    }
+
+    fn text_lines(self, kind: &str, lines: Vec<String>) -> Vec<String> {
+        match self {
+            Output::Text => lines,
+            Output::Json if lines.is_empty() => lines,
+            Output::Json => {
+                let lines: Vec<&str> = lines
+                    .iter()
+                    .map(|line| line.strip_prefix("> ").unwrap_or(line))
+                    .collect();
+
+                vec![json!({ "type": kind, "lines": lines }).to_string()]
+            }
+        }
+    }
+}
+
+fn result_json(command: &str, id: Option<CommandId>, ok: bool, result: Value) -> String {
+    let mut line = json!({ "type": "result", "command": command, "ok": ok });
+
+    if let Some(id) = id {
+        line["id"] = json!(id);
+    }
+
+    match ok {
+        true => line["result"] = result,
+        false => line["error"] = result,
+    }
+
+    line.to_string()
+}
+
+fn message_label(text: &str) -> String {
+    format!("message '{text}'")
+}
+
+/// Asks SwarmManager to leave topics and close connections and waits until it is done
+pub async fn shutdown(node: &NodeHandle) {
+    match tokio::time::timeout(SHUTDOWN_TIMEOUT, node.shutdown()).await {
+        Ok(Ok(())) => log::info!("Swarm shut down"),
+        Ok(Err(e)) => log::error!("Swarm didn't confirm shutdown: {e}"),
+        Err(_) => log::warn!("Swarm didn't shut down in {SHUTDOWN_TIMEOUT:?}"),
+    }
+}
+
+/// Response of SwarmManager as lines of the client output
+pub fn describe(ans: &impl Describe) -> Vec<String> {
+    ans.describe()
+        .into_iter()
+        .map(|line| format!("> {line}"))
+        .collect()
+}
+
+/// Response as lines, a late one with the command it answers
+pub fn format_response(response: &Response) -> Vec<String> {
+    if !response.late {
+        return response
+            .lines
+            .iter()
+            .map(|line| format!("> {line}"))
+            .collect();
+    }
+
+    let state = match response.failed {
+        true => "failed",
+        false => "done",
+    };
+
+    let mut lines = vec![format!("> #{} {} {state}:", response.id, response.command)];
+    lines.extend(response.lines.iter().map(|line| format!(">   {line}")));
+    lines
+}
+
+pub fn format_notification(notification: Notification) -> Vec<String> {
+    match notification {
+        Notification::Message(ReceivedMessage {
+            topic, sender, msg, ..
+        }) => {
+            let mut lines = vec![format!("[{}] {}: {}", topic, sender, msg)];
+
+            if let Some(file) = msg.file {
+                lines.push(format!("> sha256: {}", file.hash));
+                lines.push(format!(
+                    "> Type 'download {}' to download the file",
+                    file.short_id()
+                ));
+            }
+
+            lines
+        }
+        Notification::SyncedHistory { topic, records } => {
+            let mut lines = vec![format!(
+                "> {} earlier messages in [{topic}]:",
+                records.len()
+            )];
+            lines.extend(records.iter().map(|record| format!("> {record}")));
+            lines
+        }
+        Notification::UndecryptableMessage { topic, sender, .. } => {
+            vec![format!(
+                "> Couldn't decrypt message from {sender} in [{topic}], check the topic key"
+            )]
+        }
+        Notification::DirectMessage { sender, msg, .. } => {
+            vec![format!("[dm] {}: {}", sender, msg)]
+        }
+        Notification::PeerJoined { name, .. } => vec![format!("> {name} is online")],
+        Notification::PeerLeft { name, .. } => vec![format!("> {name} went offline")],
+        Notification::NickChanged {
+            old,
+            new,
+            conflicts,
+            ..
+        } => {
+            let mut lines = vec![format!("> {old} is now known as {new}")];
+            lines.extend(
+                conflicts
+                    .iter()
+                    .map(|(other, _)| format!("> {other} uses the same nickname")),
+            );
+            lines
+        }
+        Notification::DownloadProgress {
+            name,
+            received,
+            size,
+        } => vec![format!("> Downloading {name}: {}%", received * 100 / size)],
+        Notification::DownloadFinished { name, path, hash } => {
+            vec![format!(
+                "> File {name} saved to {}, sha256: {hash}",
+                path.display()
+            )]
+        }
+        Notification::DownloadFailed { name, reason } => {
+            vec![format!("> Couldn't download {name}: {reason}")]
+        }
+        Notification::UploadFinished { name, peer, .. } => {
+            vec![format!("> {peer} downloaded {name}")]
+        }
+    }
+}
+
+fn handle_identity_command(identity: &mut IdentityManager, cmd: IdentityCommand) -> Vec<String> {
+    match cmd {
+        IdentityCommand::Show => vec![
+            format!("> Peer id: {}", identity.peer_id()),
+            format!(
+                "> Key file: {} ({})",
+                identity.path().display(),
+                match identity.is_encrypted() {
+                    true => "encrypted",
+                    false => "not encrypted",
+                }
+            ),
+        ],
+        IdentityCommand::Export(path) => match identity.export(&path) {
+            Ok(_) => vec![format!("> Identity exported to {path}")],
+            Err(e) => {
+                log::error!("Error while exporting identity: {e}");
+                vec![format!(
+                    "> Something went wrong while exporting identity: {e}"
+                )]
+            }
+        },
+        IdentityCommand::Rotate => match identity.rotate() {
+            Ok(peer_id) => vec![format!(
+                "> New peer id: {peer_id}, it will be used after restart"
+            )],
+            Err(e) => {
+                log::error!("Error while rotating identity: {e}");
+                vec![format!(
+                    "> Something went wrong while rotating identity: {e}"
+                )]
+            }
+        },
+    }
+}
+
+/// Derives the key of a private topic from the passphrase or the first line of the key file
+async fn load_topic_key(topic: &str, source: KeySource) -> anyhow::Result<TopicKey> {
+    let passphrase = match source {
+        KeySource::Passphrase(passphrase) => passphrase,
+        KeySource::File(path) => std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("couldn't read {path}: {e}"))?
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    };
+
+    if passphrase.is_empty() {
+        anyhow::bail!("passphrase is empty");
+    }
+
+    Ok(TopicKey::derive_async(topic, &passphrase).await?)
+}
+
+pub fn available_commands() -> Vec<String> {
+    let mut lines = vec!["Available commands:".to_string()];
+
+    lines.extend(
+        parser::COMMANDS
+            .iter()
+            .map(|spec| format!("  {} - {}", spec.usage(), spec.about)),
+    );
+
+    lines.push("Plain text is sent to the active topic, start it with // to send a /".to_string());
+    lines.push("Quote arguments with spaces, /help <command> shows details".to_string());
+    lines
+}
+
+/// Error of the entered line with usage of the command
+pub fn format_error(e: &CommandLineError) -> Vec<String> {
+    let mut lines = vec![format!("> {e}")];
+
+    if let Some(spec) = e.command().and_then(parser::find) {
+        lines.push(format!("> Usage: {}", spec.usage()));
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::common::errors::{NodeError, TopicError};
+    use crate::models::common::response::SwitchOutcome;
+    use crate::models::history::history_store::HistoryStore;
+    use crate::models::node::chat_node::RunningNode;
+    use crate::models::node::test_support::{first_listen_addr, start_local_node};
+
+    #[test]
+    fn exit_codes() {
+        let codes = [
+            (Exit::Quit, 0),
+            (Exit::Failed, 2),
+            (Exit::NoPeers, 3),
+            (Exit::Interrupted, 130),
+            (Exit::Terminated, 143),
+        ];
+
+        for (exit, code) in codes {
+            assert_eq!(exit.code(), code, "{exit:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn shutdown_stops_node_after_saving_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let (first_dir, second_dir) = (dir.path().join("first"), dir.path().join("second"));
+
+        let first = start_local_node(&first_dir, Vec::new());
+        first.handle.subscribe("ops", None).await.unwrap();
+
+        let addr = first_listen_addr(&first.handle).await;
+        let RunningNode {
+            handle,
+            mut events,
+            task,
+        } = first;
+        tokio::spawn(async move { while events.recv().await.is_some() {} });
+
+        let mut second = start_local_node(&second_dir, vec![addr]);
+        second.handle.subscribe("ops", None).await.unwrap();
+        handle
+            .wait_for_topic_peers("ops", 1, Duration::from_secs(10))
+            .await
+            .unwrap();
+        handle.publish(Some("ops"), "bye").await.unwrap();
+
+        shutdown(&handle).await;
+        tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // the only peer of the second node is gone
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !matches!(
+                second.events.recv().await,
+                Some(Notification::PeerLeft { .. })
+            ) {}
+        })
+        .await
+        .unwrap();
+
+        assert!(matches!(handle.peers().await, Err(NodeError::Stopped)));
+
+        let history = HistoryStore::open(&first_dir.join("data")).unwrap();
+        let texts: Vec<_> = history
+            .last("ops", 10)
+            .unwrap()
+            .into_iter()
+            .filter_map(|record| record.text)
+            .collect();
+        assert_eq!(texts, ["bye"]);
+    }
+
+    #[test]
+    fn json_output() {
+        let ans: Result<SwitchOutcome, TopicError> =
+            Err(TopicError::NotSubscribed("ops".to_string()));
+
+        assert_eq!(
+            Output::Json.result("/switch ops", &ans),
+            vec![
+                r#"{"command":"/switch ops","error":"You are not subscribed to the topic ops","ok":false,"type":"result"}"#
+            ]
+        );
+
+        assert_eq!(
+            Output::Json.info(vec!["> Peer id: 12D3KooW".to_string()]),
+            vec![r#"{"lines":["Peer id: 12D3KooW"],"type":"info"}"#]
+        );
+        assert_eq!(
+            Output::Text.info(vec!["> Peer id: 12D3KooW".to_string()]),
+            vec!["> Peer id: 12D3KooW"]
+        );
+    }
+}
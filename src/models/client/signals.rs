@@ -0,0 +1,48 @@
+use crate::models::client::Exit;
+use std::io;
+
+/// SIGINT and SIGTERM, handled by the front ends so the terminal is restored
+/// and the swarm shuts down before exit
+pub struct Signals {
+    #[cfg(unix)]
+    interrupt: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+}
+
+impl Signals {
+    #[cfg(unix)]
+    pub fn new() -> io::Result<Self> {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {})
+    }
+
+    /// Waits for the next signal
+    #[cfg(unix)]
+    pub async fn recv(&mut self) -> Exit {
+        tokio::select! {
+            _ = self.interrupt.recv() => Exit::Interrupted,
+            _ = self.terminate.recv() => Exit::Terminated,
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub async fn recv(&mut self) -> Exit {
+        match tokio::signal::ctrl_c().await {
+            Ok(_) => Exit::Interrupted,
+            Err(e) => {
+                log::error!("Couldn't listen for Ctrl-C: {e}");
+                std::future::pending().await
+            }
+        }
+    }
+}
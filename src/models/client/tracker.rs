@@ -0,0 +1,223 @@
+use crate::models::client::describe::Describe;
+use crate::models::client::json::ToJson;
+use serde_json::{Value, json};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(3);
+const BUFFER_SIZE: usize = 30;
+/// Shown when SwarmManager dropped the command
+pub const NO_RESPONSE: &str = "No response from the swarm, try again";
+
+/// Number of a command sent to SwarmManager, shown with its late response
+pub type CommandId = u64;
+
+/// What the client learns about a command it sent
+#[derive(Debug, PartialEq)]
+pub enum Tracked {
+    /// no response in time, it comes later as a late Response with this id
+    Pending {
+        id: CommandId,
+        command: String,
+    },
+    Response(Response),
+}
+
+/// Response of SwarmManager to a command
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub id: CommandId,
+    /// command as the user entered it
+    pub command: String,
+    pub lines: Vec<String>,
+    /// result or error for the JSON output
+    pub result: Value,
+    pub failed: bool,
+    /// came after the timeout, when the command was already shown as pending
+    pub late: bool,
+}
+
+/// Waits for responses of SwarmManager in the background, so the front ends keep
+/// handling input and events, and reports them in the order they come
+pub struct CommandTracker {
+    timeout: Duration,
+    next_id: CommandId,
+    sender: mpsc::Sender<Tracked>,
+    receiver: mpsc::Receiver<Tracked>,
+}
+
+impl Default for CommandTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_RESPONSE_TIMEOUT)
+    }
+}
+
+impl Response {
+    fn new<T: Describe + ToJson>(
+        id: CommandId,
+        command: String,
+        response: Option<T>,
+        late: bool,
+    ) -> Self {
+        match response {
+            Some(response) => Self {
+                id,
+                command,
+                lines: response.describe(),
+                result: response.to_json(),
+                failed: response.failed(),
+                late,
+            },
+            None => Self {
+                id,
+                command,
+                lines: vec![NO_RESPONSE.to_string()],
+                result: json!(NO_RESPONSE),
+                failed: true,
+                late,
+            },
+        }
+    }
+}
+
+impl CommandTracker {
+    pub fn new(timeout: Duration) -> Self {
+        let (sender, receiver) = mpsc::channel(BUFFER_SIZE);
+
+        Self {
+            timeout,
+            next_id: 1,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Starts waiting for the response and returns the id it is reported with
+    pub fn track<T>(
+        &mut self,
+        command: String,
+        mut response_receiver: oneshot::Receiver<T>,
+    ) -> CommandId
+    where
+        T: Describe + ToJson + Send + 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let timeout = self.timeout;
+        let sender = self.sender.clone();
+
+        tokio::spawn(async move {
+            let (response, late) = match tokio::time::timeout(timeout, &mut response_receiver).await
+            {
+                Ok(response) => (response, false),
+                Err(_) => {
+                    log::warn!("No response to {command} in {timeout:?}, tracking it as #{id}");
+
+                    let pending = Tracked::Pending {
+                        id,
+                        command: command.clone(),
+                    };
+                    if sender.send(pending).await.is_err() {
+                        return;
+                    }
+
+                    (response_receiver.await, true)
+                }
+            };
+
+            if response.is_err() {
+                log::error!("No response to {command}, command was dropped");
+            }
+
+            let response = Response::new(id, command, response.ok(), late);
+
+            if let Err(e) = sender.send(Tracked::Response(response)).await {
+                log::error!("Response wasn't sent to client: {e}");
+            }
+        });
+
+        id
+    }
+
+    /// Next response or pending notice
+    pub async fn next(&mut self) -> Option<Tracked> {
+        self.receiver.recv().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::common::errors::TopicError;
+    use crate::models::common::response::SwitchOutcome;
+
+    type SwitchResult = Result<SwitchOutcome, TopicError>;
+
+    #[tokio::test]
+    async fn late_response_keeps_command_id() {
+        let mut tracker = CommandTracker::new(Duration::from_millis(10));
+        let (response_sender, response_receiver) = oneshot::channel::<SwitchResult>();
+
+        let id = tracker.track("/switch ops".to_string(), response_receiver);
+        assert_eq!(id, 1);
+
+        assert_eq!(
+            tracker.next().await,
+            Some(Tracked::Pending {
+                id: 1,
+                command: "/switch ops".to_string()
+            })
+        );
+
+        let _ = response_sender.send(Err(TopicError::NotSubscribed("ops".to_string())));
+
+        assert_eq!(
+            tracker.next().await,
+            Some(Tracked::Response(Response {
+                id: 1,
+                command: "/switch ops".to_string(),
+                lines: vec!["You are not subscribed to the topic ops".to_string()],
+                result: json!("You are not subscribed to the topic ops"),
+                failed: true,
+                late: true,
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn track_returns_before_the_response() {
+        let mut tracker = CommandTracker::default();
+        let (response_sender, first) = oneshot::channel::<SwitchResult>();
+        let (_, second) = oneshot::channel::<SwitchResult>();
+
+        // neither call waits for its response
+        assert_eq!(tracker.track("/switch ops".to_string(), first), 1);
+        assert_eq!(tracker.track("/switch dev".to_string(), second), 2);
+
+        let dropped = tracker.next().await;
+        assert!(matches!(
+            dropped,
+            Some(Tracked::Response(Response {
+                id: 2,
+                failed: true,
+                late: false,
+                ..
+            }))
+        ));
+
+        let _ = response_sender.send(Ok(SwitchOutcome {
+            active: "ops".to_string(),
+        }));
+        let answered = tracker.next().await;
+        assert!(matches!(
+            answered,
+            Some(Tracked::Response(Response {
+                id: 1,
+                failed: false,
+                late: false,
+                ..
+            }))
+        ));
+    }
+}
@@ -0,0 +1,546 @@
+use crate::models::command_line::tokenizer;
+use crate::models::common::errors::CommandLineError;
+
+/// Commands start with `/`, anything else is a message to the active topic
+const COMMAND_PREFIX: char = '/';
+
+#[derive(Debug, PartialEq)]
+pub enum UserCommand {
+    /// topic and key of a private topic
+    Subscribe(String, Option<KeySource>),
+    Unsubscribe(String),
+    Switch(String),
+    /// optional target topic and text
+    Msg(Option<String>, String),
+    /// peer and text
+    DirectMessage(String, String),
+    /// topic and number of messages
+    History(String, Option<usize>),
+    Search(String),
+    File(String),
+    Download(String),
+    /// multiaddr of the peer
+    Connect(String),
+    Addrs,
+    Peers,
+    /// new nickname, None to show the current one
+    Nick(Option<String>),
+    Identity(IdentityCommand),
+    /// command to describe, None to list all of them
+    Help(Option<String>),
+    Quit,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum KeySource {
+    Passphrase(String),
+    File(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum IdentityCommand {
+    Show,
+    Export(String),
+    Rotate,
+}
+
+/// Name, usage and parser of a command
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// arguments as shown in the usage
+    pub args: &'static str,
+    pub about: &'static str,
+    parse: fn(&mut Args) -> Result<UserCommand, CommandLineError>,
+}
+
+/// Arguments left after the command name
+struct Args<'a> {
+    command: &'static str,
+    rest: &'a str,
+}
+
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        name: "join",
+        aliases: &["subscribe", "j"],
+        args: "<topic> [--key <passphrase> | --key-file <path>]",
+        about: "join topic and make it active, a key makes it private and encrypted",
+        parse: |args| {
+            let topic = args.word("topic")?;
+
+            let key = match args.opt_word()? {
+                None => None,
+                Some(option) if option == "--key" => {
+                    Some(KeySource::Passphrase(args.word("passphrase")?))
+                }
+                Some(option) if option == "--key-file" => Some(KeySource::File(args.word("path")?)),
+                Some(option) if option.starts_with("--") => {
+                    return Err(CommandLineError::UnknownOption {
+                        command: args.command,
+                        option,
+                    });
+                }
+                Some(argument) => return Err(args.unexpected(argument)),
+            };
+
+            Ok(UserCommand::Subscribe(topic, key))
+        },
+    },
+    CommandSpec {
+        name: "part",
+        aliases: &["unsubscribe", "leave"],
+        args: "<topic>",
+        about: "leave topic",
+        parse: |args| Ok(UserCommand::Unsubscribe(args.word("topic")?)),
+    },
+    CommandSpec {
+        name: "switch",
+        aliases: &[],
+        args: "<topic>",
+        about: "make joined topic active",
+        parse: |args| Ok(UserCommand::Switch(args.word("topic")?)),
+    },
+    CommandSpec {
+        name: "msg",
+        aliases: &["m"],
+        args: "[#topic] <text>",
+        about: "send message to topic, active one by default",
+        parse: |args| {
+            let topic = match args.rest.trim_start().starts_with('#') {
+                true => args.word("topic")?.strip_prefix('#').map(str::to_string),
+                false => None,
+            };
+
+            Ok(UserCommand::Msg(topic, args.text("message")?))
+        },
+    },
+    CommandSpec {
+        name: "dm",
+        aliases: &["w"],
+        args: "<peer> <text>",
+        about: "send private message to nickname, peer id or short id",
+        parse: |args| {
+            let peer = args.word("peer")?;
+
+            Ok(UserCommand::DirectMessage(peer, args.text("message")?))
+        },
+    },
+    CommandSpec {
+        name: "nick",
+        aliases: &[],
+        args: "[name]",
+        about: "show or set nickname announced to peers",
+        parse: |args| Ok(UserCommand::Nick(args.opt_text())),
+    },
+    CommandSpec {
+        name: "history",
+        aliases: &[],
+        args: "<topic> [count]",
+        about: "show last messages of topic, 20 by default",
+        parse: |args| {
+            let topic = args.word("topic")?;
+            let count = args
+                .opt_word()?
+                .map(|count| args.number("count", count))
+                .transpose()?;
+
+            Ok(UserCommand::History(topic, count))
+        },
+    },
+    CommandSpec {
+        name: "search",
+        aliases: &[],
+        args: "<text>",
+        about: "find messages containing text",
+        parse: |args| Ok(UserCommand::Search(args.text("text")?)),
+    },
+    CommandSpec {
+        name: "file",
+        aliases: &[],
+        args: "<path>",
+        about: "attach file to the next message",
+        parse: |args| Ok(UserCommand::File(args.word("path")?)),
+    },
+    CommandSpec {
+        name: "download",
+        aliases: &[],
+        args: "<id>",
+        about: "download file offered in a message",
+        parse: |args| Ok(UserCommand::Download(args.word("file id")?)),
+    },
+    CommandSpec {
+        name: "connect",
+        aliases: &[],
+        args: "<multiaddr>",
+        about: "dial peer and keep reconnecting to it",
+        parse: |args| Ok(UserCommand::Connect(args.word("address")?)),
+    },
+    CommandSpec {
+        name: "peers",
+        aliases: &[],
+        args: "",
+        about: "show connected and discovered peers",
+        parse: |_| Ok(UserCommand::Peers),
+    },
+    CommandSpec {
+        name: "addrs",
+        aliases: &[],
+        args: "",
+        about: "show listen addresses and addresses observed by peers",
+        parse: |_| Ok(UserCommand::Addrs),
+    },
+    CommandSpec {
+        name: "identity",
+        aliases: &[],
+        args: "show | export <path> | rotate",
+        about: "show local peer id, copy key file or generate new identity used after restart",
+        parse: |args| {
+            let cmd = match args.word("subcommand")?.as_str() {
+                "show" => IdentityCommand::Show,
+                "export" => IdentityCommand::Export(args.word("path")?),
+                "rotate" => IdentityCommand::Rotate,
+                subcommand => {
+                    return Err(CommandLineError::UnknownSubcommand {
+                        command: args.command,
+                        subcommand: subcommand.to_string(),
+                    });
+                }
+            };
+
+            Ok(UserCommand::Identity(cmd))
+        },
+    },
+    CommandSpec {
+        name: "help",
+        aliases: &["?"],
+        args: "[command]",
+        about: "show all commands or usage of one",
+        parse: |args| {
+            let command = args
+                .opt_word()?
+                .map(|name| name.trim_start_matches(COMMAND_PREFIX).to_string());
+
+            Ok(UserCommand::Help(command))
+        },
+    },
+    CommandSpec {
+        name: "quit",
+        aliases: &["exit", "q"],
+        args: "",
+        about: "leave the chat",
+        parse: |_| Ok(UserCommand::Quit),
+    },
+];
+
+/// Parses a command or a message to the active topic. `//text` sends `/text`
+pub fn parse(input: &str) -> Result<UserCommand, CommandLineError> {
+    let input = input.trim();
+
+    if input.is_empty() {
+        return Err(CommandLineError::Empty);
+    }
+
+    let Some(line) = input.strip_prefix(COMMAND_PREFIX) else {
+        return Ok(UserCommand::Msg(None, input.to_string()));
+    };
+
+    if line.starts_with(COMMAND_PREFIX) {
+        return Ok(UserCommand::Msg(None, line.to_string()));
+    }
+
+    let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let spec = find(name).ok_or_else(|| CommandLineError::UnknownCommand(name.to_string()))?;
+
+    let mut args = Args {
+        command: spec.name,
+        rest,
+    };
+    let cmd = (spec.parse)(&mut args)?;
+    args.finish()?;
+
+    Ok(cmd)
+}
+
+/// Finds the command by name or alias, with or without `/`
+pub fn find(name: &str) -> Option<&'static CommandSpec> {
+    let name = name.trim_start_matches(COMMAND_PREFIX);
+
+    COMMANDS
+        .iter()
+        .find(|spec| spec.name == name || spec.aliases.contains(&name))
+}
+
+impl CommandSpec {
+    pub fn usage(&self) -> String {
+        format!("{COMMAND_PREFIX}{} {}", self.name, self.args)
+            .trim_end()
+            .to_string()
+    }
+
+    /// Usage, aliases and description for `/help <command>`
+    pub fn help(&self) -> Vec<String> {
+        let mut lines = vec![format!("Usage: {}", self.usage())];
+
+        if !self.aliases.is_empty() {
+            let aliases: Vec<String> = self
+                .aliases
+                .iter()
+                .map(|alias| format!("{COMMAND_PREFIX}{alias}"))
+                .collect();
+            lines.push(format!("Aliases: {}", aliases.join(", ")));
+        }
+
+        lines.push(self.about.to_string());
+        lines
+    }
+}
+
+impl Args<'_> {
+    fn word(&mut self, argument: &'static str) -> Result<String, CommandLineError> {
+        self.opt_word()?.ok_or(CommandLineError::MissingArgument {
+            command: self.command,
+            argument,
+        })
+    }
+
+    fn opt_word(&mut self) -> Result<Option<String>, CommandLineError> {
+        let input = self.rest.trim_start();
+
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        let (rest, word) = tokenizer::word(input).map_err(|_| CommandLineError::UnclosedQuote {
+            command: self.command,
+        })?;
+        self.rest = rest;
+
+        Ok(Some(word))
+    }
+
+    /// Rest of the line as typed, quotes included
+    fn text(&mut self, argument: &'static str) -> Result<String, CommandLineError> {
+        self.opt_text().ok_or(CommandLineError::MissingArgument {
+            command: self.command,
+            argument,
+        })
+    }
+
+    fn opt_text(&mut self) -> Option<String> {
+        let text = std::mem::take(&mut self.rest).trim();
+
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    fn number(&self, argument: &'static str, value: String) -> Result<usize, CommandLineError> {
+        value.parse().map_err(|_| CommandLineError::InvalidNumber {
+            command: self.command,
+            argument,
+            value,
+        })
+    }
+
+    fn unexpected(&self, argument: String) -> CommandLineError {
+        CommandLineError::UnexpectedArgument {
+            command: self.command,
+            argument,
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), CommandLineError> {
+        match self.opt_word()? {
+            Some(argument) => Err(self.unexpected(argument)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(text: &str) -> String {
+        text.to_string()
+    }
+
+    #[test]
+    fn commands() {
+        let cases = [
+            ("hello there", UserCommand::Msg(None, s("hello there"))),
+            ("//shrug", UserCommand::Msg(None, s("/shrug"))),
+            ("/join ops", UserCommand::Subscribe(s("ops"), None)),
+            ("/subscribe ops", UserCommand::Subscribe(s("ops"), None)),
+            (
+                r#"/j "ops team" --key "two words""#,
+                UserCommand::Subscribe(s("ops team"), Some(KeySource::Passphrase(s("two words")))),
+            ),
+            (
+                "/join ops --key-file ~/ops.key",
+                UserCommand::Subscribe(s("ops"), Some(KeySource::File(s("~/ops.key")))),
+            ),
+            ("/part ops", UserCommand::Unsubscribe(s("ops"))),
+            ("/leave ops", UserCommand::Unsubscribe(s("ops"))),
+            ("/switch ops", UserCommand::Switch(s("ops"))),
+            ("/msg hi all", UserCommand::Msg(None, s("hi all"))),
+            (
+                "/m #ops it's \"quoted\"",
+                UserCommand::Msg(Some(s("ops")), s("it's \"quoted\"")),
+            ),
+            (
+                "/dm alice  hi",
+                UserCommand::DirectMessage(s("alice"), s("hi")),
+            ),
+            ("/nick", UserCommand::Nick(None)),
+            ("/nick Alice B", UserCommand::Nick(Some(s("Alice B")))),
+            ("/history ops", UserCommand::History(s("ops"), None)),
+            ("/history ops 5", UserCommand::History(s("ops"), Some(5))),
+            (
+                "/search deploy failed",
+                UserCommand::Search(s("deploy failed")),
+            ),
+            (
+                r#"/file "/tmp/my report.pdf""#,
+                UserCommand::File(s("/tmp/my report.pdf")),
+            ),
+            (
+                r"/file my\ report.pdf",
+                UserCommand::File(s("my report.pdf")),
+            ),
+            ("/download 3fa2", UserCommand::Download(s("3fa2"))),
+            (
+                "/connect /ip4/10.0.0.5/tcp/4001",
+                UserCommand::Connect(s("/ip4/10.0.0.5/tcp/4001")),
+            ),
+            ("/peers", UserCommand::Peers),
+            ("  /addrs  ", UserCommand::Addrs),
+            (
+                "/identity show",
+                UserCommand::Identity(IdentityCommand::Show),
+            ),
+            (
+                "/identity export 'backup key'",
+                UserCommand::Identity(IdentityCommand::Export(s("backup key"))),
+            ),
+            (
+                "/identity rotate",
+                UserCommand::Identity(IdentityCommand::Rotate),
+            ),
+            ("/help", UserCommand::Help(None)),
+            ("/? /join", UserCommand::Help(Some(s("join")))),
+            ("/quit", UserCommand::Quit),
+            ("/q", UserCommand::Quit),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse(input), Ok(expected), "input: {input}");
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let cases = [
+            ("   ", CommandLineError::Empty),
+            ("/dance", CommandLineError::UnknownCommand(s("dance"))),
+            (
+                "/join",
+                CommandLineError::MissingArgument {
+                    command: "join",
+                    argument: "topic",
+                },
+            ),
+            (
+                "/join ops --key",
+                CommandLineError::MissingArgument {
+                    command: "join",
+                    argument: "passphrase",
+                },
+            ),
+            (
+                "/join ops --password x",
+                CommandLineError::UnknownOption {
+                    command: "join",
+                    option: s("--password"),
+                },
+            ),
+            (
+                "/join ops team",
+                CommandLineError::UnexpectedArgument {
+                    command: "join",
+                    argument: s("team"),
+                },
+            ),
+            (
+                "/file /tmp/my report.pdf",
+                CommandLineError::UnexpectedArgument {
+                    command: "file",
+                    argument: s("report.pdf"),
+                },
+            ),
+            (
+                "/file \"/tmp/my report.pdf",
+                CommandLineError::UnclosedQuote { command: "file" },
+            ),
+            (
+                "/msg #ops",
+                CommandLineError::MissingArgument {
+                    command: "msg",
+                    argument: "message",
+                },
+            ),
+            (
+                "/dm alice",
+                CommandLineError::MissingArgument {
+                    command: "dm",
+                    argument: "message",
+                },
+            ),
+            (
+                "/history ops five",
+                CommandLineError::InvalidNumber {
+                    command: "history",
+                    argument: "count",
+                    value: s("five"),
+                },
+            ),
+            (
+                "/identity delete",
+                CommandLineError::UnknownSubcommand {
+                    command: "identity",
+                    subcommand: s("delete"),
+                },
+            ),
+            (
+                "/peers all",
+                CommandLineError::UnexpectedArgument {
+                    command: "peers",
+                    argument: s("all"),
+                },
+            ),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(parse(input), Err(expected), "input: {input}");
+        }
+    }
+
+    #[test]
+    fn specs_are_unique() {
+        let mut names: Vec<&str> = COMMANDS
+            .iter()
+            .flat_map(|spec| std::iter::once(spec.name).chain(spec.aliases.iter().copied()))
+            .collect();
+        let count = names.len();
+
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), count);
+
+        assert_eq!(find("/subscribe").unwrap().name, "join");
+        assert_eq!(
+            find("join").unwrap().usage(),
+            "/join <topic> [--key <passphrase> | --key-file <path>]"
+        );
+        assert_eq!(find("peers").unwrap().usage(), "/peers");
+    }
+}
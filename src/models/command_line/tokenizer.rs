@@ -0,0 +1,102 @@
+use nom::IResult;
+use nom::branch::alt;
+use nom::bytes::complete::{escaped_transform, is_not, take_till};
+use nom::character::complete::{anychar, char};
+use nom::combinator::{map, opt, verify};
+use nom::sequence::delimited;
+
+/// One argument: `"double quoted"` with backslash escapes, `'single quoted'` taken as is,
+/// or a bare word where a backslash escapes the next character, e.g. `my\ file.txt`
+pub fn word(input: &str) -> IResult<&str, String> {
+    alt((double_quoted, single_quoted, bare))(input)
+}
+
+/// All words of `input`, None if a quote isn't closed
+pub fn words(mut input: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+
+    loop {
+        input = input.trim_start_matches([' ', '\t']);
+
+        if input.is_empty() {
+            return Some(words);
+        }
+
+        let (rest, word) = word(input).ok()?;
+        words.push(word);
+        input = rest;
+    }
+}
+
+fn double_quoted(input: &str) -> IResult<&str, String> {
+    delimited(
+        char('"'),
+        map(
+            opt(escaped_transform(is_not("\\\""), '\\', anychar)),
+            Option::unwrap_or_default,
+        ),
+        char('"'),
+    )(input)
+}
+
+fn single_quoted(input: &str) -> IResult<&str, String> {
+    delimited(
+        char('\''),
+        map(take_till(|c| c == '\''), str::to_string),
+        char('\''),
+    )(input)
+}
+
+fn bare(input: &str) -> IResult<&str, String> {
+    verify(
+        escaped_transform(is_not("\\\"' \t"), '\\', anychar),
+        |word: &String| !word.is_empty(),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_words() {
+        let cases = [
+            ("ops", "ops", ""),
+            ("ops rest", "ops", " rest"),
+            (r#""my file.txt" x"#, "my file.txt", " x"),
+            (r#""say \"hi\"""#, r#"say "hi""#, ""),
+            (r#""""#, "", ""),
+            (r"'C:\dir'", r"C:\dir", ""),
+            (r"my\ file.txt", "my file.txt", ""),
+        ];
+
+        for (input, expected, rest) in cases {
+            assert_eq!(
+                word(input),
+                Ok((rest, expected.to_string())),
+                "input: {input}"
+            );
+        }
+    }
+
+    #[test]
+    fn all_words() {
+        assert_eq!(
+            words("/join\t'ops team'  --key=a\\ b "),
+            Some(vec![
+                "/join".to_string(),
+                "ops team".to_string(),
+                "--key=a b".to_string()
+            ])
+        );
+        assert_eq!(words(" "), Some(Vec::new()));
+        assert_eq!(words(r#"/join "ops"#), None);
+    }
+
+    #[test]
+    fn unclosed_quotes() {
+        for input in [r#""open"#, "'open", r#""escaped\""#] {
+            assert!(word(input).is_err(), "input: {input}");
+        }
+    }
+}
@@ -1,47 +1,131 @@
+use crate::models::common::errors::{
+    DirectMessageError, HistoryError, NickError, SendingError, TopicError, TransferError,
+};
+use crate::models::common::file::File;
 use crate::models::common::message::Message;
+use crate::models::common::response::{
+    AddressList, ConnectOutcome, DirectMessageOutcome, NickOutcome, PeerInfo, SendOutcome,
+    SubscribeOutcome, SwitchOutcome, UnsubscribeOutcome,
+};
+use crate::models::common::topic_key::TopicKey;
+use crate::models::history::history_store::HistoryRecord;
+use libp2p::Multiaddr;
+use std::path::PathBuf;
 use tokio::sync::oneshot;
 
 #[derive(Debug)]
 pub enum Command {
     SendMessage {
-        response_sender: oneshot::Sender<String>,
+        response_sender: oneshot::Sender<Result<SendOutcome, SendingError>>,
+        /// active topic is used if None
+        topic_name: Option<String>,
         msg: Message,
     },
     Subscribe {
-        response_sender: oneshot::Sender<String>,
+        response_sender: oneshot::Sender<Result<SubscribeOutcome, TopicError>>,
         topic_name: String,
+        /// makes the topic private
+        key: Option<TopicKey>,
     },
     Unsubscribe {
-        response_sender: oneshot::Sender<String>,
+        response_sender: oneshot::Sender<Result<UnsubscribeOutcome, TopicError>>,
         topic_name: String,
     },
+    Switch {
+        response_sender: oneshot::Sender<Result<SwitchOutcome, TopicError>>,
+        topic_name: String,
+    },
+    DirectMessage {
+        /// result is sent once the peer confirms delivery
+        response_sender: oneshot::Sender<Result<DirectMessageOutcome, DirectMessageError>>,
+        /// full PeerId or its unambiguous prefix
+        peer: String,
+        msg: Message,
+    },
+    History {
+        response_sender: oneshot::Sender<Result<Vec<HistoryRecord>, HistoryError>>,
+        topic_name: String,
+        limit: usize,
+    },
+    Search {
+        response_sender: oneshot::Sender<Result<Vec<HistoryRecord>, HistoryError>>,
+        text: String,
+    },
+    Download {
+        response_sender: oneshot::Sender<Result<File, TransferError>>,
+        /// hash of the offered file or its prefix
+        file_id: String,
+        dir: PathBuf,
+    },
+    Connect {
+        response_sender: oneshot::Sender<ConnectOutcome>,
+        /// dialed now and redialed whenever the connection drops
+        addr: Multiaddr,
+    },
+    Addrs {
+        response_sender: oneshot::Sender<AddressList>,
+    },
+    Peers {
+        response_sender: oneshot::Sender<Vec<PeerInfo>>,
+    },
+    /// Number of connected peers subscribed to the topic
+    TopicPeers {
+        response_sender: oneshot::Sender<Result<usize, TopicError>>,
+        topic_name: String,
+    },
+    Nick {
+        response_sender: oneshot::Sender<Result<NickOutcome, NickError>>,
+        /// current nickname is returned if None
+        nick: Option<String>,
+    },
+    /// Leaves topics, closes connections and stops SwarmManager
+    Shutdown {
+        /// answered when everything is flushed
+        response_sender: oneshot::Sender<()>,
+    },
 }
 
 impl Command {
-    pub fn new_send_message(msg: Message) -> (Self, oneshot::Receiver<String>) {
-        let (response_sender, response_receiver) = oneshot::channel::<String>();
+    pub fn new_send_message(
+        topic_name: Option<String>,
+        msg: Message,
+    ) -> (Self, oneshot::Receiver<Result<SendOutcome, SendingError>>) {
+        let (response_sender, response_receiver) = oneshot::channel();
 
         let command = Command::SendMessage {
             response_sender,
+            topic_name,
             msg,
         };
 
         (command, response_receiver)
     }
 
-    pub fn new_subscribe(topic_name: String) -> (Self, oneshot::Receiver<String>) {
-        let (response_sender, response_receiver) = oneshot::channel::<String>();
+    pub fn new_subscribe(
+        topic_name: String,
+        key: Option<TopicKey>,
+    ) -> (
+        Self,
+        oneshot::Receiver<Result<SubscribeOutcome, TopicError>>,
+    ) {
+        let (response_sender, response_receiver) = oneshot::channel();
 
         let command = Command::Subscribe {
             response_sender,
             topic_name,
+            key,
         };
 
         (command, response_receiver)
     }
 
-    pub fn new_unsubscribe(topic_name: String) -> (Self, oneshot::Receiver<String>) {
-        let (response_sender, response_receiver) = oneshot::channel::<String>();
+    pub fn new_unsubscribe(
+        topic_name: String,
+    ) -> (
+        Self,
+        oneshot::Receiver<Result<UnsubscribeOutcome, TopicError>>,
+    ) {
+        let (response_sender, response_receiver) = oneshot::channel();
 
         let command = Command::Unsubscribe {
             response_sender,
@@ -50,4 +134,152 @@ impl Command {
 
         (command, response_receiver)
     }
+
+    pub fn new_switch(
+        topic_name: String,
+    ) -> (Self, oneshot::Receiver<Result<SwitchOutcome, TopicError>>) {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = Command::Switch {
+            response_sender,
+            topic_name,
+        };
+
+        (command, response_receiver)
+    }
+
+    pub fn new_download(
+        file_id: String,
+        dir: PathBuf,
+    ) -> (Self, oneshot::Receiver<Result<File, TransferError>>) {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = Command::Download {
+            response_sender,
+            file_id,
+            dir,
+        };
+
+        (command, response_receiver)
+    }
+
+    pub fn new_connect(addr: Multiaddr) -> (Self, oneshot::Receiver<ConnectOutcome>) {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = Command::Connect {
+            response_sender,
+            addr,
+        };
+
+        (command, response_receiver)
+    }
+
+    pub fn new_addrs() -> (Self, oneshot::Receiver<AddressList>) {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = Command::Addrs { response_sender };
+
+        (command, response_receiver)
+    }
+
+    pub fn new_peers() -> (Self, oneshot::Receiver<Vec<PeerInfo>>) {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = Command::Peers { response_sender };
+
+        (command, response_receiver)
+    }
+
+    pub fn new_topic_peers(
+        topic_name: String,
+    ) -> (Self, oneshot::Receiver<Result<usize, TopicError>>) {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = Command::TopicPeers {
+            response_sender,
+            topic_name,
+        };
+
+        (command, response_receiver)
+    }
+
+    pub fn new_shutdown() -> (Self, oneshot::Receiver<()>) {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = Command::Shutdown { response_sender };
+
+        (command, response_receiver)
+    }
+
+    pub fn new_nick(
+        nick: Option<String>,
+    ) -> (Self, oneshot::Receiver<Result<NickOutcome, NickError>>) {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = Command::Nick {
+            response_sender,
+            nick,
+        };
+
+        (command, response_receiver)
+    }
+
+    pub fn new_direct_message(
+        peer: String,
+        msg: Message,
+    ) -> (
+        Self,
+        oneshot::Receiver<Result<DirectMessageOutcome, DirectMessageError>>,
+    ) {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = Command::DirectMessage {
+            response_sender,
+            peer,
+            msg,
+        };
+
+        (command, response_receiver)
+    }
+
+    pub fn new_history(
+        topic_name: String,
+        limit: usize,
+    ) -> (
+        Self,
+        oneshot::Receiver<Result<Vec<HistoryRecord>, HistoryError>>,
+    ) {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = Command::History {
+            response_sender,
+            topic_name,
+            limit,
+        };
+
+        (command, response_receiver)
+    }
+
+    pub fn new_search(
+        text: String,
+    ) -> (
+        Self,
+        oneshot::Receiver<Result<Vec<HistoryRecord>, HistoryError>>,
+    ) {
+        let (response_sender, response_receiver) = oneshot::channel();
+
+        let command = Command::Search {
+            response_sender,
+            text,
+        };
+
+        (command, response_receiver)
+    }
+}
+
+/// Sends the result of a command back to the client
+pub fn respond<T>(response_sender: oneshot::Sender<T>, ans: T) {
+    if response_sender.send(ans).is_err() {
+        log::error!("Response wasn't sent to client");
+    }
 }
@@ -1,5 +1,7 @@
+use crate::models::common::peer_name::PeerName;
 use bincode::error::EncodeError;
-use libp2p::gossipsub::PublishError;
+use libp2p::gossipsub::{PublishError, SubscriptionError};
+use std::path::PathBuf;
 
 #[derive(Debug, thiserror::Error)]
 pub enum FileError {
@@ -7,14 +9,237 @@ pub enum FileError {
     CouldntReadFile,
     #[error("Filename is not in UTF-8")]
     WrongEncoding,
+    #[error("Filename is empty")]
+    EmptyFileName,
+    #[error("Filename contains control characters")]
+    InvalidFileName,
+    #[error("Filename {0} is reserved")]
+    ReservedFileName(String),
+    #[error("File path is outside of the download directory")]
+    PathOutsideDownloadDir,
+    #[error("Couldn't find free name for the file")]
+    NoFreeFileName,
+    #[error("Download directory is not available")]
+    InvalidDownloadDir,
+    #[error("Couldn't create file: {0}")]
+    CouldntCreateFile(std::io::Error),
+    #[error("File size {actual} doesn't match offered size {expected}")]
+    SizeMismatch { expected: u64, actual: u64 },
+    #[error("File hash {actual} doesn't match offered hash {expected}")]
+    HashMismatch { expected: String, actual: String },
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum SendingError {
     #[error("Swarm is not subscribed to the topic")]
     NoSubscribedTopic,
+    #[error("Swarm is not subscribed to the topic {0}")]
+    NotSubscribedTo(String),
     #[error("Message encoding error")]
     CantEncodeMessage(#[from] EncodeError),
+    #[error("Message encryption error: {0}")]
+    CantEncryptMessage(#[from] TopicKeyError),
     #[error("Other error {0}")]
     Other(#[from] PublishError),
 }
+
+#[derive(Debug, thiserror::Error)]
+pub enum IdentityError {
+    #[error("Couldn't read key file: {0}")]
+    CouldntReadKeyFile(std::io::Error),
+    #[error("Couldn't write key file: {0}")]
+    CouldntWriteKeyFile(std::io::Error),
+    #[error("Key file is corrupted")]
+    CorruptedKeyFile,
+    #[error("Key file is encrypted, passphrase required")]
+    PassphraseRequired,
+    #[error("Wrong passphrase or corrupted key file")]
+    WrongPassphrase,
+    #[error("Couldn't derive key from passphrase")]
+    KeyDerivation,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TransferError {
+    #[error("No file offer with id {0}")]
+    UnknownFile(String),
+    #[error("File id {0} is ambiguous, enter more symbols")]
+    AmbiguousFileId(String),
+    #[error("File is already being downloaded")]
+    AlreadyDownloading,
+    #[error("Couldn't write file: {0}")]
+    CouldntWriteFile(std::io::Error),
+    #[error("Couldn't save file: {0}")]
+    CouldntSaveFile(#[from] FileError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DirectMessageError {
+    #[error("No known peer matches {0}")]
+    UnknownPeer(String),
+    #[error("Peer {0} is ambiguous, enter more symbols")]
+    AmbiguousPeer(String),
+    #[error("Couldn't deliver message to {peer}: {reason}")]
+    NotDelivered { peer: PeerName, reason: String },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TopicError {
+    #[error("Not subscribed to the topic {0}")]
+    NotSubscribed(String),
+    #[error("Couldn't subscribe to the topic: {0}")]
+    CantSubscribe(#[from] SubscriptionError),
+}
+
+/// Error of a NodeHandle call, wraps the error of the command
+#[derive(Debug, thiserror::Error)]
+pub enum NodeError {
+    #[error("Node is stopped")]
+    Stopped,
+    #[error("Found {found} of {wanted} peers subscribed to the topic {topic}")]
+    NotEnoughPeers {
+        topic: String,
+        found: usize,
+        wanted: usize,
+    },
+    #[error(transparent)]
+    Sending(#[from] SendingError),
+    #[error(transparent)]
+    Topic(#[from] TopicError),
+    #[error(transparent)]
+    DirectMessage(#[from] DirectMessageError),
+    #[error(transparent)]
+    History(#[from] HistoryError),
+    #[error(transparent)]
+    Transfer(#[from] TransferError),
+    #[error(transparent)]
+    Nick(#[from] NickError),
+    #[error(transparent)]
+    File(#[from] FileError),
+}
+
+/// Error of the control API, sent to the caller as the error of the response
+#[derive(Debug, thiserror::Error)]
+pub enum ControlError {
+    #[error("Invalid JSON: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    #[error("Message has neither text nor file")]
+    EmptyMessage,
+    #[error(transparent)]
+    TopicKey(#[from] TopicKeyError),
+    #[error(transparent)]
+    Node(#[from] NodeError),
+    #[error("Control socket {0} is used by a running node")]
+    SocketInUse(PathBuf),
+    #[error("{0} isn't a socket, refusing to replace it")]
+    NotASocket(PathBuf),
+    #[error("Couldn't find file {path}: {source}")]
+    FileNotFound {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Couldn't connect to {path}, is the daemon running? {source}")]
+    CouldntConnect {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Control socket error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Daemon closed the connection")]
+    Closed,
+    /// error the daemon answered with
+    #[error("{0}")]
+    Failed(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShortPeerIdError {
+    #[error("Short peer id is empty")]
+    Empty,
+    #[error("Short peer id can't contain '{0}'")]
+    InvalidChar(char),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("Couldn't create data dir: {0}")]
+    CouldntCreateDataDir(std::io::Error),
+    #[error("History database error: {0}")]
+    Database(#[from] rusqlite::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TopicKeyError {
+    #[error("Couldn't derive key from passphrase")]
+    KeyDerivation,
+    #[error("Couldn't encrypt message")]
+    CantEncrypt,
+    #[error("Couldn't decrypt message, wrong key or corrupted message")]
+    CantDecrypt,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NickError {
+    #[error("Nickname is empty")]
+    Empty,
+    #[error(
+        "Nickname is longer than {} characters",
+        crate::models::common::peer_name::MAX_NICK_LEN
+    )]
+    TooLong,
+    #[error("Nickname contains control or invisible formatting characters")]
+    InvalidChars,
+    #[error("Nickname reads as a peer id, add a character that isn't in base58 like '_' or 'l'")]
+    LooksLikePeerId,
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum CommandLineError {
+    #[error("Nothing entered")]
+    Empty,
+    #[error("Unknown command /{0}, type /help to see all commands")]
+    UnknownCommand(String),
+    #[error("Missing {argument}")]
+    MissingArgument {
+        command: &'static str,
+        argument: &'static str,
+    },
+    #[error("Unexpected argument {argument}, quote arguments with spaces")]
+    UnexpectedArgument {
+        command: &'static str,
+        argument: String,
+    },
+    #[error("Unknown option {option}")]
+    UnknownOption {
+        command: &'static str,
+        option: String,
+    },
+    #[error("Unknown subcommand {subcommand}")]
+    UnknownSubcommand {
+        command: &'static str,
+        subcommand: String,
+    },
+    #[error("{argument} must be a number, got {value}")]
+    InvalidNumber {
+        command: &'static str,
+        argument: &'static str,
+        value: String,
+    },
+    #[error("Quote isn't closed")]
+    UnclosedQuote { command: &'static str },
+}
+
+impl CommandLineError {
+    /// Command whose usage should be shown with the error
+    pub fn command(&self) -> Option<&'static str> {
+        match self {
+            CommandLineError::Empty | CommandLineError::UnknownCommand(_) => None,
+            CommandLineError::MissingArgument { command, .. }
+            | CommandLineError::UnexpectedArgument { command, .. }
+            | CommandLineError::UnknownOption { command, .. }
+            | CommandLineError::UnknownSubcommand { command, .. }
+            | CommandLineError::InvalidNumber { command, .. }
+            | CommandLineError::UnclosedQuote { command } => Some(command),
+        }
+    }
+}
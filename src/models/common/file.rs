@@ -1,19 +1,39 @@
 use crate::models::common::errors::FileError;
-use serde::{Deserialize, Serialize};
-use std::path::Path;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncReadExt;
 
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+/// Length of a hex encoded SHA-256
+const HASH_LEN: usize = 64;
+/// Max number in `name (n).ext` tried when the name is taken
+const MAX_NAME_SUFFIX: u32 = 1000;
+/// Names that can't be used for files on Windows, with or without extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// File offer attached to a message. Content isn't sent over gossipsub,
+/// peers interested in the file download it from the sender in chunks
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct File {
     pub name: String,
-    pub data: Vec<u8>,
+    pub size: u64,
+    /// hex encoded SHA-256 of the content, also used as the file id.
+    /// Lowercased when received, so offers compare equal to computed hashes
+    #[serde(deserialize_with = "lowercase")]
+    pub hash: String,
+    /// local path of the shared file, known only to the sender
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
 }
 
 impl File {
     pub async fn from(path: impl AsRef<Path> + Clone) -> Result<Self, FileError> {
-        let data = tokio::fs::read(path.clone())
-            .await
-            .map_err(|_| FileError::CouldntReadFile)?;
-
         let name = path
             .as_ref()
             .file_name()
@@ -22,25 +42,192 @@ impl File {
             .ok_or(FileError::WrongEncoding)?
             .to_owned();
 
-        Ok(Self { name, data })
+        let mut file = tokio::fs::File::open(path.clone())
+            .await
+            .map_err(|_| FileError::CouldntReadFile)?;
+
+        let mut hasher = ContentHasher::default();
+        let mut buff = vec![0u8; READ_BUFFER_SIZE];
+
+        loop {
+            let read = file
+                .read(&mut buff)
+                .await
+                .map_err(|_| FileError::CouldntReadFile)?;
+
+            if read == 0 {
+                break;
+            }
+
+            hasher.update(&buff[..read]);
+        }
+
+        let (size, hash) = hasher.finish();
+
+        let path = tokio::fs::canonicalize(path)
+            .await
+            .map_err(|_| FileError::CouldntReadFile)?;
+
+        Ok(Self {
+            name,
+            size,
+            hash,
+            path: Some(path),
+        })
     }
 
-    pub async fn save(self, path: &Path) -> bool {
-        let full_path = path.join(self.name.clone());
+    /// Checks that the received content is exactly what the sender offered
+    pub fn verify(&self, content: ContentHasher) -> Result<(), FileError> {
+        let (size, hash) = content.finish();
 
-        match tokio::fs::write(full_path, self.data).await {
-            Ok(_) => {
-                log::info!("File: {} saved successfully", self.name);
-                true
+        if size != self.size {
+            return Err(FileError::SizeMismatch {
+                expected: self.size,
+                actual: size,
+            });
+        }
+
+        if hash != self.hash {
+            return Err(FileError::HashMismatch {
+                expected: self.hash.clone(),
+                actual: hash,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Short form of the hash shown to users and accepted by the download command
+    pub fn short_id(&self) -> &str {
+        self.hash.get(..8).unwrap_or(&self.hash)
+    }
+
+    /// Name the file can be saved under. The name comes from a remote peer,
+    /// so only its last component is used
+    pub fn safe_name(&self) -> Result<String, FileError> {
+        sanitize_name(&self.name)
+    }
+
+    /// Creates an empty file inside `dir` for the download and returns its path.
+    /// Taken names get a ` (n)` suffix, the file is created only if it doesn't exist
+    /// yet, so nothing can take the name between the check and the final rename
+    pub async fn save_path(&self, dir: &Path) -> Result<PathBuf, FileError> {
+        let name = self.safe_name()?;
+
+        let dir = tokio::fs::canonicalize(dir)
+            .await
+            .map_err(|_| FileError::InvalidDownloadDir)?;
+
+        let (stem, extension) = match name.rfind('.') {
+            Some(dot) if dot > 0 => name.split_at(dot),
+            _ => (name.as_str(), ""),
+        };
+
+        for n in 0..=MAX_NAME_SUFFIX {
+            let candidate = match n {
+                0 => dir.join(&name),
+                n => dir.join(format!("{stem} ({n}){extension}")),
+            };
+
+            if candidate.parent() != Some(dir.as_path()) {
+                return Err(FileError::PathOutsideDownloadDir);
             }
-            Err(e) => {
-                log::error!("Error while saving file: {e}");
-                false
+
+            let created = tokio::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&candidate)
+                .await;
+
+            match created {
+                Ok(_) => return Ok(candidate),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(FileError::CouldntCreateFile(e)),
             }
         }
+
+        Err(FileError::NoFreeFileName)
+    }
+
+    pub fn has_valid_hash(&self) -> bool {
+        is_hex_hash(&self.hash)
+    }
+}
+
+fn sanitize_name(name: &str) -> Result<String, FileError> {
+    let name = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .trim_end_matches('.');
+
+    if name.is_empty() {
+        return Err(FileError::EmptyFileName);
+    }
+
+    if name.chars().any(char::is_control) {
+        return Err(FileError::InvalidFileName);
+    }
+
+    let base = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES.contains(&base.to_uppercase().as_str()) {
+        return Err(FileError::ReservedFileName(name.to_string()));
+    }
+
+    Ok(name.to_string())
+}
+
+impl Display for File {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({} bytes, id {})",
+            self.name,
+            self.size,
+            self.short_id()
+        )
+    }
+}
+
+/// SHA-256 and size of file content fed in chunks
+#[derive(Default)]
+pub struct ContentHasher {
+    hasher: Sha256,
+    size: u64,
+}
+
+impl ContentHasher {
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.hasher.update(chunk);
+        self.size += chunk.len() as u64;
+    }
+
+    /// Returns size and hex encoded hash
+    pub fn finish(self) -> (u64, String) {
+        (self.size, to_hex(&self.hasher.finalize()))
     }
 }
 
+fn is_hex_hash(hash: &str) -> bool {
+    hash.len() == HASH_LEN && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Hashes come from remote peers, anything but a hex SHA-256 is refused
+fn lowercase<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let hash = String::deserialize(deserializer)?;
+    if !is_hex_hash(&hash) {
+        return Err(D::Error::custom(format!(
+            "file hash must be {HASH_LEN} hex characters"
+        )));
+    }
+    Ok(hash.to_ascii_lowercase())
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -59,7 +246,11 @@ mod tests {
         let result = File::from(&path).await.unwrap();
 
         assert_eq!(result.name, path.file_name().unwrap().to_str().unwrap());
-        assert_eq!(result.data, test_data);
+        assert_eq!(result.size, test_data.len() as u64);
+        assert_eq!(
+            result.hash,
+            "64ec88ca00b268e5ba1a35678a1b5316d212f4f366b2477232534a8aeca37f3c"
+        );
     }
 
     #[tokio::test]
@@ -94,4 +285,193 @@ mod tests {
 
         tokio::fs::remove_file(&path).await.unwrap();
     }
+
+    fn offer(name: &str) -> File {
+        File {
+            name: name.to_string(),
+            size: 0,
+            hash: String::new(),
+            path: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_path_strips_components() {
+        let dir = tempfile::tempdir().unwrap();
+        let canonical_dir = dir.path().canonicalize().unwrap();
+
+        for name in [
+            "../../.bashrc",
+            "/etc/.bashrc",
+            "..\\..\\.bashrc",
+            "sub/dir/.bashrc",
+        ] {
+            let path = offer(name).save_path(dir.path()).await.unwrap();
+            assert_eq!(path, canonical_dir.join(".bashrc"), "{name}");
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_path_rejects_empty_names() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for name in ["", "..", ".", "../", "dir/..", "  "] {
+            let result = offer(name).save_path(dir.path()).await;
+            assert!(matches!(result, Err(FileError::EmptyFileName)), "{name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_path_rejects_reserved_names() {
+        let dir = tempfile::tempdir().unwrap();
+
+        for name in ["CON", "nul.txt", "com1.tar.gz", "Lpt9 "] {
+            let result = offer(name).save_path(dir.path()).await;
+            assert!(
+                matches!(result, Err(FileError::ReservedFileName(_))),
+                "{name}"
+            );
+        }
+
+        let result = offer("console.txt").save_path(dir.path()).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_save_path_rejects_control_characters() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let result = offer("evil\nname.txt").save_path(dir.path()).await;
+        assert!(matches!(result, Err(FileError::InvalidFileName)));
+    }
+
+    #[tokio::test]
+    async fn test_save_path_invalid_dir() {
+        let result = offer("a.txt")
+            .save_path(Path::new("/non/existent/dir"))
+            .await;
+        assert!(matches!(result, Err(FileError::InvalidDownloadDir)));
+    }
+
+    #[tokio::test]
+    async fn test_save_path_resolves_collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let canonical_dir = dir.path().canonicalize().unwrap();
+
+        std::fs::write(dir.path().join("report.txt"), b"").unwrap();
+        std::fs::write(dir.path().join("report (1).txt"), b"").unwrap();
+        std::fs::write(dir.path().join(".hidden"), b"").unwrap();
+        std::fs::write(dir.path().join("archive.tar.gz"), b"").unwrap();
+
+        let cases = [
+            ("report.txt", "report (2).txt"),
+            (".hidden", ".hidden (1)"),
+            ("archive.tar.gz", "archive.tar (1).gz"),
+            ("new.txt", "new.txt"),
+        ];
+
+        for (name, expected) in cases {
+            let path = offer(name).save_path(dir.path()).await.unwrap();
+            assert_eq!(path, canonical_dir.join(expected), "{name}");
+        }
+
+        // the returned path is taken, so the next download of the same name gets another one
+        let path = offer("new.txt").save_path(dir.path()).await.unwrap();
+        assert_eq!(path, canonical_dir.join("new (1).txt"));
+    }
+
+    #[test]
+    fn test_hash_validation() {
+        let mut file = offer("a.txt");
+
+        file.hash = "ab".repeat(32);
+        assert!(file.has_valid_hash());
+
+        for hash in ["", "abc123", &"ab".repeat(33), &"zz".repeat(32)] {
+            file.hash = hash.to_string();
+            assert!(!file.has_valid_hash(), "{hash}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_content() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"Hello world").unwrap();
+
+        let offer = File::from(file.path()).await.unwrap();
+
+        let mut content = ContentHasher::default();
+        content.update(b"Hello ");
+        content.update(b"world");
+        assert!(offer.verify(content).is_ok());
+
+        let mut content = ContentHasher::default();
+        content.update(b"Hello World");
+        assert!(matches!(
+            offer.verify(content),
+            Err(FileError::HashMismatch { .. })
+        ));
+
+        let mut content = ContentHasher::default();
+        content.update(b"Hello");
+        assert!(matches!(
+            offer.verify(content),
+            Err(FileError::SizeMismatch {
+                expected: 11,
+                actual: 5
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_path_is_not_serialized() {
+        use crate::traits::decode::Decode;
+        use crate::traits::encode::Encode;
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"data").unwrap();
+
+        let offer = File::from(file.path()).await.unwrap();
+        assert!(offer.path.is_some());
+
+        let decoded = File::decode(&mut offer.encode_to_vec().unwrap().as_slice()).unwrap();
+        assert_eq!(decoded.hash, offer.hash);
+        assert!(decoded.path.is_none());
+    }
+
+    #[test]
+    fn test_invalid_hash_is_not_decoded() {
+        use crate::traits::decode::Decode;
+        use crate::traits::encode::Encode;
+
+        let mut file = offer("a.txt");
+        for hash in ["aaaaaaa€", "abc123", &"zz".repeat(32)] {
+            file.hash = hash.to_string();
+            let data = file.encode_to_vec().unwrap();
+            assert!(File::decode(&mut data.as_slice()).is_err(), "{hash}");
+        }
+
+        file.hash = "aaaaaaa€".to_string();
+        assert_eq!(file.short_id(), "aaaaaaa€");
+    }
+
+    #[tokio::test]
+    async fn test_uppercase_offer_verifies() {
+        use crate::traits::decode::Decode;
+        use crate::traits::encode::Encode;
+
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"Hello world").unwrap();
+
+        let mut offer = File::from(file.path()).await.unwrap();
+        offer.hash = offer.hash.to_ascii_uppercase();
+
+        let received = File::decode(&mut offer.encode_to_vec().unwrap().as_slice()).unwrap();
+        assert!(received.has_valid_hash());
+
+        let mut content = ContentHasher::default();
+        content.update(b"Hello world");
+        assert!(received.verify(content).is_ok());
+    }
 }
@@ -1,7 +1,8 @@
 use crate::models::common::file::File;
+use crate::models::common::peer_name::PeerName;
+use libp2p::PeerId;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
-use std::path::Path;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct Message {
@@ -9,6 +10,20 @@ pub struct Message {
     pub file: Option<File>,
 }
 
+/// Message delivered to the client with the topic it arrived on
+#[derive(Debug)]
+pub struct ReceivedMessage {
+    /// gossipsub message id, also the id in the history
+    pub id: String,
+    pub topic: String,
+    pub sender: PeerName,
+    /// full PeerId of the author
+    pub sender_id: PeerId,
+    /// unix time in seconds the message was received at
+    pub timestamp: i64,
+    pub msg: Message,
+}
+
 impl Message {
     pub async fn build(data: Option<String>, file: Option<File>) -> Self {
         Self { data, file }
@@ -17,26 +32,19 @@ impl Message {
     pub fn is_empty(&self) -> bool {
         self.data.is_none() && self.file.is_none()
     }
-
-    pub async fn try_to_download_file(self, path: &Path) -> bool {
-        if let Some(file) = self.file {
-            return file.save(path).await;
-        }
-        false
-    }
 }
 
 impl Display for Message {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match (&self.data, &self.file) {
             (Some(data), Some(file)) => {
-                write!(f, "{}, File: {}", data, file.name)
+                write!(f, "{}, File: {}", data, file)
             }
             (Some(data), None) => {
                 write!(f, "{}", data)
             }
             (None, Some(file)) => {
-                write!(f, "File: {}", file.name)
+                write!(f, "File: {}", file)
             }
             (None, None) => {
                 write!(f, "*Empty message*")
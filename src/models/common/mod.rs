@@ -2,4 +2,8 @@ pub mod command;
 pub mod errors;
 pub mod file;
 pub mod message;
+pub mod notification;
+pub mod peer_name;
+pub mod response;
 pub mod short_peer_id;
+pub mod topic_key;
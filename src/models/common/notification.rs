@@ -0,0 +1,71 @@
+use crate::models::common::message::{Message, ReceivedMessage};
+use crate::models::common::peer_name::PeerName;
+use crate::models::history::history_store::HistoryRecord;
+use libp2p::PeerId;
+use std::path::PathBuf;
+
+/// Everything SwarmManager reports to the client besides command responses
+#[derive(Debug)]
+pub enum Notification {
+    Message(ReceivedMessage),
+    /// messages of the topic sent before this node joined, oldest first
+    SyncedHistory {
+        topic: String,
+        records: Vec<HistoryRecord>,
+    },
+    /// message in a private topic that couldn't be decrypted with the local key
+    UndecryptableMessage {
+        topic: String,
+        sender: PeerName,
+        sender_id: PeerId,
+        /// unix time in seconds the message was received at
+        timestamp: i64,
+    },
+    DirectMessage {
+        /// local id of the received message
+        id: String,
+        sender: PeerName,
+        sender_id: PeerId,
+        /// unix time in seconds the message was received at
+        timestamp: i64,
+        msg: Message,
+    },
+    /// first connection to the peer was established
+    PeerJoined {
+        name: PeerName,
+        peer_id: PeerId,
+    },
+    /// last connection to the peer was closed
+    PeerLeft {
+        name: PeerName,
+        peer_id: PeerId,
+    },
+    /// peer announced a new nickname, `conflicts` are other peers using the same one
+    NickChanged {
+        peer_id: PeerId,
+        old: PeerName,
+        new: PeerName,
+        conflicts: Vec<(PeerName, PeerId)>,
+    },
+    DownloadProgress {
+        name: String,
+        received: u64,
+        size: u64,
+    },
+    DownloadFinished {
+        name: String,
+        path: PathBuf,
+        /// verified SHA-256 of the saved file
+        hash: String,
+    },
+    DownloadFailed {
+        name: String,
+        reason: String,
+    },
+    /// peer was sent the last chunk of a file shared by this node
+    UploadFinished {
+        name: String,
+        peer: PeerName,
+        peer_id: PeerId,
+    },
+}
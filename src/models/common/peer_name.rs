@@ -0,0 +1,156 @@
+use crate::models::common::errors::NickError;
+use crate::models::common::short_peer_id::ShortPeerId;
+use libp2p::PeerId;
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+
+pub const MAX_NICK_LEN: usize = 32;
+/// Unicode general category Cf as of Unicode 15.1: invisible characters and bidi controls
+/// that would let a nickname look like another one
+const FORMAT_CHARS: &[RangeInclusive<char>] = &[
+    '\u{00AD}'..='\u{00AD}',
+    '\u{0600}'..='\u{0605}',
+    '\u{061C}'..='\u{061C}',
+    '\u{06DD}'..='\u{06DD}',
+    '\u{070F}'..='\u{070F}',
+    '\u{0890}'..='\u{0891}',
+    '\u{08E2}'..='\u{08E2}',
+    '\u{180E}'..='\u{180E}',
+    '\u{200B}'..='\u{200F}',
+    '\u{202A}'..='\u{202E}',
+    '\u{2060}'..='\u{2064}',
+    '\u{2066}'..='\u{206F}',
+    '\u{FEFF}'..='\u{FEFF}',
+    '\u{FFF9}'..='\u{FFFB}',
+    '\u{110BD}'..='\u{110BD}',
+    '\u{110CD}'..='\u{110CD}',
+    '\u{13430}'..='\u{1343F}',
+    '\u{1BCA0}'..='\u{1BCA3}',
+    '\u{1D173}'..='\u{1D17A}',
+    '\u{E0001}'..='\u{E0001}',
+    '\u{E0020}'..='\u{E007F}',
+];
+
+/// How a peer is shown to the user: `alice (…x7Fq9a)` if the nickname is known, `…x7Fq9a` otherwise
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerName {
+    nick: Option<String>,
+    short_id: ShortPeerId,
+}
+
+impl PeerName {
+    pub fn new(short_id: ShortPeerId, nick: Option<&str>) -> Self {
+        Self {
+            nick: nick.map(str::to_string),
+            short_id,
+        }
+    }
+
+    pub fn nick(&self) -> Option<&str> {
+        self.nick.as_deref()
+    }
+}
+
+impl Display for PeerName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.nick {
+            Some(nick) => write!(f, "{nick} ({})", self.short_id),
+            None => write!(f, "{}", self.short_id),
+        }
+    }
+}
+
+/// Trims the nickname and checks it can be shown as is
+pub fn validate_nick(nick: &str) -> Result<String, NickError> {
+    let nick = nick.trim();
+
+    if nick.is_empty() {
+        return Err(NickError::Empty);
+    }
+
+    // a nickname equal to an id would be taken for another peer in /dm
+    if nick.parse::<PeerId>().is_ok() || nick.parse::<ShortPeerId>().is_ok() {
+        return Err(NickError::LooksLikePeerId);
+    }
+
+    if nick.chars().count() > MAX_NICK_LEN {
+        return Err(NickError::TooLong);
+    }
+
+    if nick.chars().any(|c| c.is_control() || is_format(c)) {
+        return Err(NickError::InvalidChars);
+    }
+
+    Ok(nick.to_string())
+}
+
+fn is_format(c: char) -> bool {
+    FORMAT_CHARS.iter().any(|range| range.contains(&c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nick_is_shown_with_short_id() {
+        let short_id: ShortPeerId = "x7Fq9a".parse().unwrap();
+
+        let name = PeerName::new(short_id.clone(), Some("alice"));
+        assert_eq!(name.to_string(), "alice (…x7Fq9a)");
+
+        let name = PeerName::new(short_id, None);
+        assert_eq!(name.to_string(), "…x7Fq9a");
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(validate_nick("  alice ").unwrap(), "alice");
+        assert!(matches!(validate_nick(" "), Err(NickError::Empty)));
+        assert!(matches!(
+            validate_nick("a\u{1b}[31m"),
+            Err(NickError::InvalidChars)
+        ));
+        assert!(matches!(
+            validate_nick(&"l".repeat(MAX_NICK_LEN + 1)),
+            Err(NickError::TooLong)
+        ));
+    }
+
+    #[test]
+    fn ids_are_rejected() {
+        let peer_id = PeerId::random().to_string();
+        let tail = &peer_id[peer_id.len() - 6..];
+
+        for nick in [
+            peer_id.as_str(),
+            tail,
+            &format!("…{tail}"),
+            &format!("...{tail}"),
+        ] {
+            assert!(
+                matches!(validate_nick(nick), Err(NickError::LooksLikePeerId)),
+                "{nick}"
+            );
+        }
+
+        assert!(validate_nick(&format!("{tail}_")).is_ok());
+    }
+
+    #[test]
+    fn format_characters_are_rejected() {
+        for nick in [
+            "ali\u{202E}ecb",
+            "al\u{200B}ice",
+            "\u{FEFF}alice",
+            "alice\u{E0041}",
+        ] {
+            assert!(
+                matches!(validate_nick(nick), Err(NickError::InvalidChars)),
+                "{nick:?}"
+            );
+        }
+
+        assert_eq!(validate_nick("Zoë ☕").unwrap(), "Zoë ☕");
+    }
+}
@@ -0,0 +1,76 @@
+use crate::models::common::peer_name::PeerName;
+use libp2p::{Multiaddr, PeerId};
+use std::time::Duration;
+
+/// Message published to a topic
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendOutcome {
+    pub message_id: String,
+    pub topic: String,
+    /// peers subscribed to the topic when the message was published
+    pub recipients: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscribeOutcome {
+    Subscribed,
+    /// messages are encrypted and history isn't synced
+    SubscribedPrivate,
+    AlreadySubscribed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnsubscribeOutcome {
+    /// topic that is active after unsubscribing
+    pub active: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwitchOutcome {
+    pub active: String,
+}
+
+/// Peer confirmed it received the direct message
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectMessageOutcome {
+    pub recipient: PeerName,
+    pub peer_id: PeerId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectOutcome {
+    Dialing(Multiaddr),
+    AlreadyKnown(Multiaddr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NickOutcome {
+    Current(Option<String>),
+    Changed(String),
+}
+
+/// Addresses the local node listens on and the ones other peers see it at
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AddressList {
+    pub listen: Vec<Multiaddr>,
+    pub observed: Vec<Multiaddr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+/// Connected or discovered peer
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerInfo {
+    pub name: PeerName,
+    pub peer_id: PeerId,
+    /// direction of the first connection, None if the peer is only discovered
+    pub connection: Option<Direction>,
+    pub rtt: Option<Duration>,
+    /// names of the topics the peer is subscribed to, hashes for unknown ones
+    pub topics: Vec<String>,
+    pub addresses: Vec<Multiaddr>,
+}
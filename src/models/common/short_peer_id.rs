@@ -1,17 +1,137 @@
+use crate::models::common::errors::ShortPeerIdError;
 use libp2p::PeerId;
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Eq)]
+/// Every Ed25519 PeerId starts with `12D3KooW`, so the tail is used instead
+pub const DEFAULT_SHORT_ID_LEN: usize = 6;
+const ELLIPSIS: &str = "…";
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Last characters of a PeerId, shown as `…x7Fq9a`
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ShortPeerId(String);
 
 impl ShortPeerId {
-    pub fn from(peer_id: &PeerId) -> Self {
-        Self(format!("{}...", &peer_id.to_string()[..8]))
+    /// Shortest id of at least `min_len` characters that no other of `known` peers ends with
+    pub fn unique<'a>(
+        peer_id: &PeerId,
+        known: impl IntoIterator<Item = &'a PeerId>,
+        min_len: usize,
+    ) -> Self {
+        let others: Vec<String> = known
+            .into_iter()
+            .filter(|other| *other != peer_id)
+            .map(ToString::to_string)
+            .collect();
+
+        let full = peer_id.to_string();
+
+        (min_len.max(1)..full.len())
+            .map(|len| &full[full.len() - len..])
+            .find(|suffix| others.iter().all(|other| !other.ends_with(suffix)))
+            .map(|suffix| Self(suffix.to_string()))
+            .unwrap_or(Self(full))
+    }
+
+    pub fn matches(&self, peer_id: &PeerId) -> bool {
+        peer_id.to_string().ends_with(&self.0)
     }
 }
 
 impl Display for ShortPeerId {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{ELLIPSIS}{}", self.0)
+    }
+}
+
+/// Accepts the displayed form with `…` or `...` in front as well as the bare tail
+impl FromStr for ShortPeerId {
+    type Err = ShortPeerIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tail = s
+            .trim()
+            .trim_start_matches(ELLIPSIS)
+            .trim_start_matches("...");
+
+        if tail.is_empty() {
+            return Err(ShortPeerIdError::Empty);
+        }
+
+        if let Some(c) = tail.chars().find(|c| !BASE58_ALPHABET.contains(*c)) {
+            return Err(ShortPeerIdError::InvalidChar(c));
+        }
+
+        Ok(Self(tail.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two different peers whose ids end with the same character
+    fn colliding_peers() -> (PeerId, PeerId) {
+        let first = PeerId::random();
+        let last = first.to_string().pop();
+
+        loop {
+            let second = PeerId::random();
+            if second.to_string().pop() == last {
+                return (first, second);
+            }
+        }
+    }
+
+    #[test]
+    fn uses_tail_of_peer_id() {
+        let peer_id = PeerId::random();
+
+        let short = ShortPeerId::unique(&peer_id, [], DEFAULT_SHORT_ID_LEN);
+
+        assert_eq!(short.0.len(), DEFAULT_SHORT_ID_LEN);
+        assert!(peer_id.to_string().ends_with(&short.0));
+        assert!(short.to_string().starts_with(ELLIPSIS));
+        assert!(short.matches(&peer_id));
+    }
+
+    #[test]
+    fn unique_extends_on_collision() {
+        let (first, second) = colliding_peers();
+        let known = [first, second];
+
+        let short_first = ShortPeerId::unique(&first, &known, 1);
+        let short_second = ShortPeerId::unique(&second, &known, 1);
+
+        assert!(short_first.0.len() > 1);
+        assert!(short_first.matches(&first));
+        assert!(!short_first.matches(&second));
+        assert!(!short_second.matches(&first));
+
+        let alone = ShortPeerId::unique(&first, [&first], 1);
+        assert_eq!(alone.0.len(), 1);
+    }
+
+    #[test]
+    fn parse_back() {
+        let peer_id = PeerId::random();
+        let short = ShortPeerId::unique(&peer_id, [], 8);
+
+        assert_eq!(short.to_string().parse::<ShortPeerId>().unwrap(), short);
+        assert_eq!(
+            format!("...{}", short.0).parse::<ShortPeerId>().unwrap(),
+            short
+        );
+        assert_eq!(short.0.parse::<ShortPeerId>().unwrap(), short);
+
+        assert!(matches!(
+            "…".parse::<ShortPeerId>(),
+            Err(ShortPeerIdError::Empty)
+        ));
+        assert!(matches!(
+            "…x0".parse::<ShortPeerId>(),
+            Err(ShortPeerIdError::InvalidChar('0'))
+        ));
     }
 }
@@ -0,0 +1,167 @@
+use crate::models::common::errors::TopicKeyError;
+use crate::models::common::file::to_hex;
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::fmt::{Debug, Formatter};
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+const WIRE_NAME_PREFIX: &str = "p2p-chat/private/";
+
+/// Symmetric key of a private topic, derived from a passphrase shared out of band.
+/// Every member derives the same key, so the salt comes from the topic name.
+/// The gossipsub topic is keyed too, so peers without the passphrase neither share it
+/// nor can confirm a guessed topic name
+pub struct TopicKey {
+    cipher: ChaCha20Poly1305,
+    wire_name: String,
+}
+
+impl TopicKey {
+    pub fn derive(topic_name: &str, passphrase: &str) -> Result<Self, TopicKeyError> {
+        let salt = Sha256::new()
+            .chain_update("p2p-chat topic salt:")
+            .chain_update(topic_name)
+            .finalize();
+
+        // the first half encrypts messages, the second one names the gossipsub topic
+        let mut key = [0u8; 2 * KEY_LEN];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt[..SALT_LEN], &mut key)
+            .map_err(|_| TopicKeyError::KeyDerivation)?;
+
+        let (cipher_key, name_key) = key.split_at(KEY_LEN);
+
+        Ok(Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(cipher_key)),
+            wire_name: wire_name(name_key, topic_name)?,
+        })
+    }
+
+    /// Same as `derive` on the blocking thread pool, Argon2 would stall the async task
+    pub async fn derive_async(topic_name: &str, passphrase: &str) -> Result<Self, TopicKeyError> {
+        let (topic_name, passphrase) = (topic_name.to_string(), passphrase.to_string());
+
+        tokio::task::spawn_blocking(move || Self::derive(&topic_name, &passphrase))
+            .await
+            .map_err(|_| TopicKeyError::KeyDerivation)?
+    }
+
+    /// Gossipsub topic used instead of the plain name
+    pub fn wire_name(&self) -> &str {
+        &self.wire_name
+    }
+
+    /// Random nonce followed by the ciphertext, bound to the topic
+    pub fn seal(&self, data: &[u8]) -> Result<Vec<u8>, TopicKeyError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+
+        let payload = Payload {
+            msg: data,
+            aad: self.wire_name.as_bytes(),
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .map_err(|_| TopicKeyError::CantEncrypt)?;
+
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    pub fn open(&self, data: &[u8]) -> Result<Vec<u8>, TopicKeyError> {
+        if data.len() < NONCE_LEN {
+            return Err(TopicKeyError::CantDecrypt);
+        }
+
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: self.wire_name.as_bytes(),
+        };
+
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| TopicKeyError::CantDecrypt)
+    }
+}
+
+impl Debug for TopicKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TopicKey")
+            .field("wire_name", &self.wire_name)
+            .finish_non_exhaustive()
+    }
+}
+
+fn wire_name(name_key: &[u8], topic_name: &str) -> Result<String, TopicKeyError> {
+    let mac = <Hmac<Sha256> as Mac>::new_from_slice(name_key)
+        .map_err(|_| TopicKeyError::KeyDerivation)?
+        .chain_update(topic_name)
+        .finalize();
+
+    Ok(format!("{WIRE_NAME_PREFIX}{}", to_hex(&mac.into_bytes())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seal_and_open() {
+        let key = TopicKey::derive("ops", "secret").unwrap();
+
+        let sealed = key.seal(b"hello").unwrap();
+
+        assert_ne!(&sealed[NONCE_LEN..], b"hello");
+        assert_eq!(key.open(&sealed).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn wrong_key_or_topic_fails() {
+        let key = TopicKey::derive("ops", "secret").unwrap();
+        let sealed = key.seal(b"hello").unwrap();
+
+        let wrong_passphrase = TopicKey::derive("ops", "guess").unwrap();
+        assert!(wrong_passphrase.open(&sealed).is_err());
+
+        let other_topic = TopicKey::derive("dev", "secret").unwrap();
+        assert!(other_topic.open(&sealed).is_err());
+
+        assert!(key.open(&sealed[..NONCE_LEN - 1]).is_err());
+    }
+
+    #[test]
+    fn wire_name_depends_on_key() {
+        let key = TopicKey::derive("ops", "secret").unwrap();
+
+        assert!(!key.wire_name().contains("ops"));
+        assert_eq!(
+            key.wire_name(),
+            TopicKey::derive("ops", "secret").unwrap().wire_name()
+        );
+        assert_ne!(
+            key.wire_name(),
+            TopicKey::derive("ops", "other").unwrap().wire_name()
+        );
+        assert_ne!(
+            key.wire_name(),
+            TopicKey::derive("dev", "secret").unwrap().wire_name()
+        );
+    }
+
+    #[tokio::test]
+    async fn derive_async_matches_derive() {
+        let key = TopicKey::derive_async("ops", "secret").await.unwrap();
+        let sealed = key.seal(b"hello").unwrap();
+
+        let same = TopicKey::derive("ops", "secret").unwrap();
+        assert_eq!(same.wire_name(), key.wire_name());
+        assert_eq!(same.open(&sealed).unwrap(), b"hello");
+    }
+}
@@ -0,0 +1,79 @@
+use crate::models::common::errors::ControlError;
+use crate::models::control::protocol::{Request, RequestLine, Response};
+use serde_json::Value;
+use std::path::Path;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::UnixStream;
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+
+/// Connection to the control API of a running daemon
+pub struct ControlClient {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+    next_id: u64,
+}
+
+impl ControlClient {
+    pub async fn connect(path: impl AsRef<Path>) -> Result<Self, ControlError> {
+        let path = path.as_ref();
+        let stream =
+            UnixStream::connect(path)
+                .await
+                .map_err(|source| ControlError::CouldntConnect {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+
+        let (reader, writer) = stream.into_split();
+
+        Ok(Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            next_id: 1,
+        })
+    }
+
+    /// Sends the request and waits for its result, events that come first are skipped
+    pub async fn request(&mut self, request: Request) -> Result<Value, ControlError> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut line = serde_json::to_string(&RequestLine {
+            id: Some(id),
+            request,
+        })?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await?;
+
+        loop {
+            let line = self.lines.next_line().await?.ok_or(ControlError::Closed)?;
+
+            // events have no `ok` field
+            let Ok(response) = serde_json::from_str::<Response>(&line) else {
+                continue;
+            };
+
+            if response.id != Some(id) {
+                continue;
+            }
+
+            return match response.ok {
+                true => Ok(response.result.unwrap_or_default()),
+                false => Err(ControlError::Failed(response.error.unwrap_or_default())),
+            };
+        }
+    }
+
+    /// Next event after `Request::Events`, None once the daemon stops
+    pub async fn next_event(&mut self) -> Result<Option<Value>, ControlError> {
+        while let Some(line) = self.lines.next_line().await? {
+            let value: Value = serde_json::from_str(&line)?;
+
+            if value.get("event").is_some() {
+                return Ok(Some(value));
+            }
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+
+/// Command of the control API, one JSON object per line, e.g.
+/// `{"id": 1, "cmd": "send", "topic": "ops", "text": "hello"}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    /// `key` is the passphrase of a private topic
+    Subscribe {
+        topic: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key: Option<Passphrase>,
+    },
+    Unsubscribe {
+        topic: String,
+    },
+    /// Publishes to the topic or to the active one, the file is offered to peers
+    Send {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        topic: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        text: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file: Option<PathBuf>,
+    },
+    DirectMessage {
+        peer: String,
+        text: String,
+    },
+    Peers,
+    /// Streams events of the node to this connection after the response
+    Events,
+    /// Stops the node and the daemon
+    Shutdown,
+}
+
+/// Topic passphrase, left out when the request is logged
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Passphrase(pub String);
+
+impl Debug for Passphrase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+/// Request with the id its response is sent with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestLine {
+    #[serde(default)]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub request: Request,
+}
+
+/// Answer to a request, events are sent as objects with an `event` field instead
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub id: Option<u64>,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Response {
+    pub fn ok(id: Option<u64>, result: Value) -> Self {
+        Self {
+            id,
+            ok: true,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: Option<u64>, error: impl ToString) -> Self {
+        Self {
+            id,
+            ok: false,
+            result: None,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_request_line() {
+        let line: RequestLine =
+            serde_json::from_str(r#"{"id": 7, "cmd": "send", "text": "hello"}"#).unwrap();
+
+        assert_eq!(
+            line,
+            RequestLine {
+                id: Some(7),
+                request: Request::Send {
+                    topic: None,
+                    text: Some("hello".to_string()),
+                    file: None,
+                },
+            }
+        );
+
+        let line: RequestLine = serde_json::from_str(r#"{"cmd": "peers"}"#).unwrap();
+        assert_eq!(line.request, Request::Peers);
+
+        assert!(serde_json::from_str::<RequestLine>(r#"{"cmd": "fly"}"#).is_err());
+    }
+
+    #[test]
+    fn passphrase_is_not_logged() {
+        let line: RequestLine =
+            serde_json::from_str(r#"{"cmd": "subscribe", "topic": "ops", "key": "hunter2"}"#)
+                .unwrap();
+
+        assert_eq!(
+            line.request,
+            Request::Subscribe {
+                topic: "ops".to_string(),
+                key: Some(Passphrase("hunter2".to_string())),
+            }
+        );
+        assert!(!format!("{line:?}").contains("hunter2"));
+    }
+}
@@ -0,0 +1,366 @@
+use crate::models::client::json::ToJson;
+use crate::models::client::signals::Signals;
+use crate::models::client::{Exit, shutdown};
+use crate::models::common::errors::ControlError;
+use crate::models::common::topic_key::TopicKey;
+use crate::models::control::protocol::{Request, RequestLine, Response};
+use crate::models::node::handle::{NodeEvents, NodeHandle};
+use serde_json::Value;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+
+/// Events kept for a connection that reads them slower than they come
+const EVENT_BUFFER_SIZE: usize = 100;
+
+/// Control API of a node on a Unix socket, takes the place of the interactive client
+pub struct ControlServer {
+    listener: UnixListener,
+    path: PathBuf,
+    node: NodeHandle,
+    events: NodeEvents,
+}
+
+/// Client connected to the socket
+struct Connection {
+    node: NodeHandle,
+    event_sender: broadcast::Sender<String>,
+    stop_sender: mpsc::Sender<()>,
+}
+
+impl ControlServer {
+    /// Listens on the socket, replacing one left by a node that didn't stop cleanly
+    pub fn bind(
+        path: impl Into<PathBuf>,
+        node: NodeHandle,
+        events: NodeEvents,
+    ) -> Result<Self, ControlError> {
+        let path = path.into();
+        remove_stale_socket(&path)?;
+
+        let listener = bind_private(&path)?;
+        log::info!("Control API listens on {}", path.display());
+
+        Ok(Self {
+            listener,
+            path,
+            node,
+            events,
+        })
+    }
+
+    /// Serves connections until a signal or a shutdown request comes, then shuts down the node
+    pub async fn run(mut self) -> Exit {
+        let mut signals = match Signals::new() {
+            Ok(signals) => signals,
+            Err(e) => {
+                log::error!("Couldn't listen for signals: {e}");
+                shutdown(&self.node).await;
+                return Exit::Failed;
+            }
+        };
+
+        let (event_sender, _) = broadcast::channel::<String>(EVENT_BUFFER_SIZE);
+        let (stop_sender, mut stop_receiver) = mpsc::channel::<()>(1);
+
+        let exit = loop {
+            tokio::select! {
+                conn = self.listener.accept() => match conn {
+                    Ok((stream, _)) => {
+                        let connection = Connection {
+                            node: self.node.clone(),
+                            event_sender: event_sender.clone(),
+                            stop_sender: stop_sender.clone(),
+                        };
+
+                        tokio::spawn(connection.serve(stream));
+                    }
+                    Err(e) => log::error!("Couldn't accept control connection: {e}"),
+                },
+                notification = self.events.recv() => match notification {
+                    // nobody listening for events is fine
+                    Some(notification) => _ = event_sender.send(notification.to_json().to_string()),
+                    None => {
+                        log::error!("Node stopped on its own");
+                        break Exit::Failed;
+                    }
+                },
+                _ = stop_receiver.recv() => break Exit::Quit,
+                exit = signals.recv() => break exit,
+            }
+        };
+
+        shutdown(&self.node).await;
+
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::warn!("Couldn't remove control socket: {e}");
+        }
+
+        exit
+    }
+}
+
+impl Connection {
+    /// Answers requests line by line and forwards events once they are requested
+    async fn serve(self, stream: UnixStream) {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let mut event_receiver = None;
+
+        loop {
+            let line = tokio::select! {
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) if line.trim().is_empty() => continue,
+                    Ok(Some(line)) => {
+                        let response = self.handle(&line, &mut event_receiver).await;
+                        serde_json::to_string(&response).unwrap_or_default()
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::warn!("Couldn't read control request: {e}");
+                        break;
+                    }
+                },
+                event = next_event(&mut event_receiver) => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(n)) => {
+                        log::warn!("Control client is too slow, {n} events were dropped");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            };
+
+            if let Err(e) = write_line(&mut writer, &line).await {
+                log::warn!("Couldn't answer control client: {e}");
+                break;
+            }
+        }
+    }
+
+    async fn handle(
+        &self,
+        line: &str,
+        event_receiver: &mut Option<broadcast::Receiver<String>>,
+    ) -> Response {
+        let RequestLine { id, request } = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => return Response::error(None, ControlError::InvalidJson(e)),
+        };
+
+        // passphrases are redacted
+        log::info!("Control request {request:?}");
+
+        match self.execute(request, event_receiver).await {
+            Ok(result) => Response::ok(id, result),
+            Err(e) => Response::error(id, e),
+        }
+    }
+
+    async fn execute(
+        &self,
+        request: Request,
+        event_receiver: &mut Option<broadcast::Receiver<String>>,
+    ) -> Result<Value, ControlError> {
+        let result = match request {
+            Request::Subscribe { topic, key } => {
+                let key = match key {
+                    Some(passphrase) => Some(TopicKey::derive_async(&topic, &passphrase.0).await?),
+                    None => None,
+                };
+
+                self.node.subscribe(&topic, key).await?.to_json()
+            }
+            Request::Unsubscribe { topic } => self.node.unsubscribe(&topic).await?.to_json(),
+            Request::Send {
+                topic,
+                text,
+                file: Some(path),
+            } => self
+                .node
+                .send_file(topic.as_deref(), path, text)
+                .await?
+                .to_json(),
+            Request::Send {
+                topic,
+                text: Some(text),
+                file: None,
+            } => self.node.publish(topic.as_deref(), &text).await?.to_json(),
+            Request::Send { .. } => return Err(ControlError::EmptyMessage),
+            Request::DirectMessage { peer, text } => {
+                self.node.direct_message(&peer, &text).await?.to_json()
+            }
+            Request::Peers => self.node.peers().await?.to_json(),
+            Request::Events => {
+                *event_receiver = Some(self.event_sender.subscribe());
+                Value::Null
+            }
+            Request::Shutdown => {
+                // full channel means the daemon is already stopping
+                let _ = self.stop_sender.try_send(());
+                Value::Null
+            }
+        };
+
+        Ok(result)
+    }
+}
+
+/// Binds the socket in a directory only the user can enter and links it to `path`
+/// once only the user may connect, so it is never open to others. Unlike a rename,
+/// the link fails if anything was put at `path` after the stale socket check
+fn bind_private(path: &Path) -> Result<UnixListener, ControlError> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let dir = parent.join(format!(".p2p-chat-socket-{}", std::process::id()));
+    std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+
+    let private_path = dir.join("socket");
+    let listener = UnixListener::bind(&private_path).and_then(|listener| {
+        std::fs::set_permissions(&private_path, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::hard_link(&private_path, path)?;
+        Ok(listener)
+    });
+
+    // the socket is linked to `path` or was never bound
+    let _ = std::fs::remove_file(&private_path);
+    if let Err(e) = std::fs::remove_dir(&dir) {
+        log::warn!("Couldn't remove {}: {e}", dir.display());
+    }
+
+    listener.map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => match std::fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => {
+                ControlError::SocketInUse(path.to_path_buf())
+            }
+            _ => ControlError::NotASocket(path.to_path_buf()),
+        },
+        _ => e.into(),
+    })
+}
+
+/// Removes the socket file if no node listens on it, anything else at the path is kept
+fn remove_stale_socket(path: &Path) -> Result<(), ControlError> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    if !metadata.file_type().is_socket() {
+        return Err(ControlError::NotASocket(path.to_path_buf()));
+    }
+
+    if std::os::unix::net::UnixStream::connect(path).is_ok() {
+        return Err(ControlError::SocketInUse(path.to_path_buf()));
+    }
+
+    log::warn!("Removing stale control socket {}", path.display());
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Next event for the connection, never comes until events are requested
+async fn next_event(
+    event_receiver: &mut Option<broadcast::Receiver<String>>,
+) -> Result<String, RecvError> {
+    match event_receiver {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn write_line(writer: &mut OwnedWriteHalf, line: &str) -> std::io::Result<()> {
+    writer.write_all(line.as_bytes()).await?;
+    writer.write_all(b"\n").await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::control::client::ControlClient;
+    use crate::models::control::protocol::Passphrase;
+    use crate::models::node::test_support::start_local_node;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn requests_are_answered_over_the_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("control.sock");
+
+        let node = start_local_node(dir.path(), Vec::new());
+        let server = ControlServer::bind(&path, node.handle, node.events).unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let server = tokio::spawn(server.run());
+        let mut client = ControlClient::connect(&path).await.unwrap();
+
+        let subscribed = client
+            .request(Request::Subscribe {
+                topic: "ops".to_string(),
+                key: Some(Passphrase("hunter2".to_string())),
+            })
+            .await
+            .unwrap();
+        assert_eq!(subscribed, json!({ "status": "subscribed_private" }));
+
+        assert_eq!(client.request(Request::Peers).await.unwrap(), json!([]));
+
+        let empty = client
+            .request(Request::Send {
+                topic: None,
+                text: None,
+                file: None,
+            })
+            .await;
+        assert!(
+            matches!(empty, Err(ControlError::Failed(e)) if e == "Message has neither text nor file")
+        );
+
+        client.request(Request::Shutdown).await.unwrap();
+
+        assert_eq!(server.await.unwrap(), Exit::Quit);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn only_sockets_are_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "keep me").unwrap();
+
+        assert!(matches!(
+            remove_stale_socket(&path),
+            Err(ControlError::NotASocket(_))
+        ));
+        assert!(path.exists());
+
+        let stale = dir.path().join("stale.sock");
+        drop(std::os::unix::net::UnixListener::bind(&stale).unwrap());
+
+        remove_stale_socket(&stale).unwrap();
+        assert!(!stale.exists());
+    }
+
+    #[tokio::test]
+    async fn file_created_after_the_check_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("control.sock");
+        std::fs::write(&path, "keep me").unwrap();
+
+        assert!(matches!(
+            bind_private(&path),
+            Err(ControlError::NotASocket(_))
+        ));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,367 @@
+use crate::models::common::errors::HistoryError;
+use crate::models::common::message::Message;
+use jiff::Timestamp;
+use jiff::tz::TimeZone;
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+const DB_FILE_NAME: &str = "history.db";
+
+/// Stored topic message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub message_id: String,
+    pub topic: String,
+    /// full PeerId of the author
+    pub sender: String,
+    /// unix time in seconds
+    pub timestamp: i64,
+    pub text: Option<String>,
+    pub file_name: Option<String>,
+    /// false if the record came from history sync, whose sender and timestamp
+    /// are only claimed by the serving peer
+    #[serde(default)]
+    pub verified: bool,
+    /// decrypted message of a private topic, never served to syncing peers
+    /// even if a public topic has the same name
+    #[serde(default)]
+    pub private: bool,
+}
+
+impl HistoryRecord {
+    pub fn new(
+        message_id: String,
+        topic: String,
+        sender: String,
+        msg: &Message,
+        private: bool,
+    ) -> Self {
+        Self {
+            message_id,
+            topic,
+            sender,
+            timestamp: Timestamp::now().as_second(),
+            text: msg.data.clone(),
+            file_name: msg.file.as_ref().map(|file| file.name.clone()),
+            verified: true,
+            private,
+        }
+    }
+}
+
+impl Display for HistoryRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let time = Timestamp::from_second(self.timestamp)
+            .map(|ts| {
+                ts.to_zoned(TimeZone::system())
+                    .strftime("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|_| self.timestamp.to_string());
+
+        write!(f, "{time} [{}] {}", self.topic, self.sender)?;
+        if !self.verified {
+            write!(f, " (unverified)")?;
+        }
+        write!(f, ": ")?;
+
+        match (&self.text, &self.file_name) {
+            (Some(text), Some(file_name)) => write!(f, "{text}, File: {file_name}"),
+            (Some(text), None) => write!(f, "{text}"),
+            (None, Some(file_name)) => write!(f, "File: {file_name}"),
+            (None, None) => write!(f, "*Empty message*"),
+        }
+    }
+}
+
+/// Sent and received topic messages kept in SQLite under the data dir
+pub struct HistoryStore {
+    conn: Connection,
+}
+
+impl HistoryStore {
+    pub fn open(data_dir: &Path) -> Result<Self, HistoryError> {
+        std::fs::create_dir_all(data_dir).map_err(HistoryError::CouldntCreateDataDir)?;
+
+        Self::init(Connection::open(data_dir.join(DB_FILE_NAME))?)
+    }
+
+    /// History that is lost when the node stops
+    pub fn in_memory() -> Result<Self, HistoryError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, HistoryError> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS messages (
+                message_id TEXT PRIMARY KEY,
+                topic TEXT NOT NULL,
+                sender TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                text TEXT,
+                file_name TEXT,
+                verified INTEGER NOT NULL,
+                private INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS messages_topic_timestamp
+                ON messages (topic, timestamp);",
+        )?;
+
+        // older stores mixed synced records with received ones
+        add_column(&conn, "verified", "INTEGER NOT NULL DEFAULT 0")?;
+        // and didn't tell private topics from public ones of the same name
+        add_column(&conn, "private", "INTEGER NOT NULL DEFAULT 1")?;
+
+        Ok(Self { conn })
+    }
+
+    /// Writes cached pages to the database file
+    pub fn flush(&self) -> Result<(), HistoryError> {
+        Ok(self.conn.cache_flush()?)
+    }
+
+    /// Returns false if a message with the same id is already stored
+    pub fn insert(&self, record: &HistoryRecord) -> Result<bool, HistoryError> {
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO messages
+                (message_id, topic, sender, timestamp, text, file_name, verified, private)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                record.message_id,
+                record.topic,
+                record.sender,
+                record.timestamp,
+                record.text,
+                record.file_name,
+                record.verified,
+                record.private
+            ],
+        )?;
+
+        Ok(inserted > 0)
+    }
+
+    /// Last `limit` messages of the topic, oldest first
+    pub fn last(&self, topic: &str, limit: usize) -> Result<Vec<HistoryRecord>, HistoryError> {
+        self.last_of(topic, limit, true)
+    }
+
+    /// Last `limit` messages of the public topic, oldest first, to serve to syncing peers
+    pub fn last_public(
+        &self,
+        topic: &str,
+        limit: usize,
+    ) -> Result<Vec<HistoryRecord>, HistoryError> {
+        self.last_of(topic, limit, false)
+    }
+
+    fn last_of(
+        &self,
+        topic: &str,
+        limit: usize,
+        with_private: bool,
+    ) -> Result<Vec<HistoryRecord>, HistoryError> {
+        self.query(
+            "SELECT * FROM (
+                SELECT message_id, topic, sender, timestamp, text, file_name, verified, private,
+                    rowid
+                FROM messages
+                WHERE topic = ?1 AND (private = 0 OR ?3)
+                ORDER BY timestamp DESC, rowid DESC LIMIT ?2
+            ) ORDER BY timestamp, rowid",
+            params![topic, limit, with_private],
+        )
+    }
+
+    /// Last `limit` messages in any topic containing `text`, oldest first
+    pub fn search(&self, text: &str, limit: usize) -> Result<Vec<HistoryRecord>, HistoryError> {
+        let pattern = format!(
+            "%{}%",
+            text.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+
+        self.query(
+            "SELECT * FROM (
+                SELECT message_id, topic, sender, timestamp, text, file_name, verified, private,
+                    rowid
+                FROM messages
+                WHERE text LIKE ?1 ESCAPE '\\' OR file_name LIKE ?1 ESCAPE '\\'
+                ORDER BY timestamp DESC, rowid DESC LIMIT ?2
+            ) ORDER BY timestamp, rowid",
+            params![pattern, limit],
+        )
+    }
+
+    fn query(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<HistoryRecord>, HistoryError> {
+        let mut statement = self.conn.prepare(sql)?;
+
+        let records = statement
+            .query_map(params, |row| {
+                Ok(HistoryRecord {
+                    message_id: row.get(0)?,
+                    topic: row.get(1)?,
+                    sender: row.get(2)?,
+                    timestamp: row.get(3)?,
+                    text: row.get(4)?,
+                    file_name: row.get(5)?,
+                    verified: row.get(6)?,
+                    private: row.get(7)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(records)
+    }
+}
+
+/// Adds the column to the messages table of a store created before it existed
+fn add_column(conn: &Connection, name: &str, definition: &str) -> Result<(), HistoryError> {
+    let exists = conn
+        .prepare("SELECT 1 FROM pragma_table_info('messages') WHERE name = ?1")?
+        .exists([name])?;
+
+    if !exists {
+        conn.execute_batch(&format!(
+            "ALTER TABLE messages ADD COLUMN {name} {definition}"
+        ))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(message_id: &str, topic: &str, timestamp: i64, text: &str) -> HistoryRecord {
+        HistoryRecord {
+            message_id: message_id.to_string(),
+            topic: topic.to_string(),
+            sender: "peer".to_string(),
+            timestamp,
+            text: Some(text.to_string()),
+            file_name: None,
+            verified: true,
+            private: false,
+        }
+    }
+
+    #[test]
+    fn insert_ignores_duplicates() {
+        let store = HistoryStore::in_memory().unwrap();
+
+        assert!(store.insert(&record("1", "ops", 1, "hello")).unwrap());
+        assert!(!store.insert(&record("1", "ops", 1, "hello")).unwrap());
+
+        assert_eq!(store.last("ops", 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn last_returns_newest_in_order() {
+        let store = HistoryStore::in_memory().unwrap();
+
+        store.insert(&record("1", "ops", 1, "first")).unwrap();
+        store.insert(&record("2", "ops", 3, "third")).unwrap();
+        store.insert(&record("3", "ops", 2, "second")).unwrap();
+        store.insert(&record("4", "dev", 4, "other topic")).unwrap();
+
+        let texts: Vec<_> = store
+            .last("ops", 2)
+            .unwrap()
+            .into_iter()
+            .filter_map(|r| r.text)
+            .collect();
+
+        assert_eq!(texts, vec!["second", "third"]);
+    }
+
+    #[test]
+    fn search_in_all_topics() {
+        let store = HistoryStore::in_memory().unwrap();
+
+        store.insert(&record("1", "ops", 1, "deploy done")).unwrap();
+        store
+            .insert(&record("2", "dev", 2, "Deploy failed"))
+            .unwrap();
+        store.insert(&record("3", "dev", 3, "lunch?")).unwrap();
+        store.insert(&record("4", "dev", 4, "100% done")).unwrap();
+
+        let found = store.search("deploy", 10).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].topic, "ops");
+
+        let found = store.search("%", 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].message_id, "4");
+    }
+
+    #[test]
+    fn old_store_gets_new_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open(dir.path().join(DB_FILE_NAME)).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE messages (
+                message_id TEXT PRIMARY KEY,
+                topic TEXT NOT NULL,
+                sender TEXT NOT NULL,
+                timestamp INTEGER NOT NULL,
+                text TEXT,
+                file_name TEXT
+            );
+            INSERT INTO messages VALUES ('1', 'ops', 'peer', 1, 'hello', NULL);",
+        )
+        .unwrap();
+        drop(conn);
+
+        let store = HistoryStore::open(dir.path()).unwrap();
+        store.insert(&record("2", "ops", 2, "again")).unwrap();
+
+        let flags: Vec<_> = store
+            .last("ops", 10)
+            .unwrap()
+            .iter()
+            .map(|r| (r.verified, r.private))
+            .collect();
+        assert_eq!(flags, [(false, true), (true, false)]);
+    }
+
+    #[test]
+    fn private_messages_are_not_public() {
+        let store = HistoryStore::in_memory().unwrap();
+
+        store.insert(&record("1", "ops", 1, "public")).unwrap();
+        store
+            .insert(&HistoryRecord {
+                private: true,
+                ..record("2", "ops", 2, "secret")
+            })
+            .unwrap();
+
+        assert_eq!(store.last("ops", 10).unwrap().len(), 2);
+        let public = store.last_public("ops", 10).unwrap();
+        assert_eq!(public.len(), 1);
+        assert_eq!(public[0].text.as_deref(), Some("public"));
+    }
+
+    #[test]
+    fn history_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let store = HistoryStore::open(dir.path()).unwrap();
+        store.insert(&record("1", "ops", 1, "hello")).unwrap();
+        drop(store);
+
+        let store = HistoryStore::open(dir.path()).unwrap();
+        assert_eq!(
+            store.last("ops", 10).unwrap()[0].text.as_deref(),
+            Some("hello")
+        );
+    }
+}
@@ -0,0 +1 @@
+pub mod history_store;
@@ -0,0 +1,253 @@
+use crate::models::common::errors::IdentityError;
+use crate::models::identity::key_file::KeyFile;
+use libp2p::PeerId;
+use libp2p::identity::Keypair;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub struct IdentityManager {
+    keypair: Keypair,
+    path: PathBuf,
+    passphrase: Option<String>,
+}
+
+impl IdentityManager {
+    /// Loads the keypair from `path`, generating and storing a new Ed25519 keypair on first run
+    pub fn load_or_generate(
+        path: impl Into<PathBuf>,
+        passphrase: Option<String>,
+    ) -> Result<Self, IdentityError> {
+        let path = path.into();
+
+        let (keypair, passphrase) = if path.exists() {
+            let key_file = KeyFile::read(&path)?;
+            let was_encrypted = key_file.is_encrypted();
+
+            let key = key_file.open(passphrase.as_deref())?;
+            let keypair = Keypair::from_protobuf_encoding(&key)
+                .map_err(|_| IdentityError::CorruptedKeyFile)?;
+
+            // a passphrase given for a plain key file protects it from now on
+            if !was_encrypted && let Some(passphrase) = passphrase.as_deref() {
+                write_keypair(&keypair, &path, Some(passphrase))?;
+                log::warn!(
+                    "Key file {} wasn't encrypted, encrypted it with the passphrase",
+                    path.display()
+                );
+            }
+
+            log::info!("Identity loaded from {}", path.display());
+            (keypair, passphrase)
+        } else {
+            let keypair = Keypair::generate_ed25519();
+            write_keypair(&keypair, &path, passphrase.as_deref())?;
+            log::info!("New identity generated and saved to {}", path.display());
+            (keypair, passphrase)
+        };
+
+        Ok(Self {
+            keypair,
+            path,
+            passphrase,
+        })
+    }
+
+    pub fn keypair(&self) -> &Keypair {
+        &self.keypair
+    }
+
+    pub fn peer_id(&self) -> PeerId {
+        self.keypair.public().to_peer_id()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.passphrase.is_some()
+    }
+
+    /// Writes a copy of the key file, protected with the same passphrase, to `path`
+    pub fn export(&self, path: impl AsRef<Path>) -> Result<(), IdentityError> {
+        write_keypair(&self.keypair, path.as_ref(), self.passphrase.as_deref())
+    }
+
+    /// Replaces the stored keypair with a freshly generated one and keeps the old key file
+    /// next to it with a `.old` extension, numbered `.old.1`, `.old.2`, ... if that is taken.
+    /// The running swarm keeps its identity until restart.
+    pub fn rotate(&mut self) -> Result<PeerId, IdentityError> {
+        let data = std::fs::read(&self.path).map_err(IdentityError::CouldntReadKeyFile)?;
+        write_backup(&self.path, &data)?;
+
+        let keypair = Keypair::generate_ed25519();
+        write_keypair(&keypair, &self.path, self.passphrase.as_deref())?;
+
+        self.keypair = keypair;
+
+        Ok(self.peer_id())
+    }
+}
+
+fn write_keypair(
+    keypair: &Keypair,
+    path: &Path,
+    passphrase: Option<&str>,
+) -> Result<(), IdentityError> {
+    let key = keypair
+        .to_protobuf_encoding()
+        .map_err(|_| IdentityError::CorruptedKeyFile)?;
+
+    KeyFile::seal(key, passphrase)?.write(path)
+}
+
+/// Writes `data` to the first free backup path, never replacing an earlier backup
+fn write_backup(path: &Path, data: &[u8]) -> Result<(), IdentityError> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    for n in 0.. {
+        let mut backup = path.as_os_str().to_owned();
+        match n {
+            0 => backup.push(".old"),
+            n => backup.push(format!(".old.{n}")),
+        }
+
+        match options.open(&backup) {
+            Ok(mut file) => {
+                return file
+                    .write_all(data)
+                    .map_err(IdentityError::CouldntWriteKeyFile);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(IdentityError::CouldntWriteKeyFile(e)),
+        }
+    }
+
+    unreachable!("backup numbers are unbounded")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate_and_load_same_identity() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.key");
+
+        let first = IdentityManager::load_or_generate(&path, None).unwrap();
+        let second = IdentityManager::load_or_generate(&path, None).unwrap();
+
+        assert_eq!(first.peer_id(), second.peer_id());
+    }
+
+    #[test]
+    fn encrypted_identity() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.key");
+
+        let first = IdentityManager::load_or_generate(&path, Some("secret".to_string())).unwrap();
+
+        let second = IdentityManager::load_or_generate(&path, Some("secret".to_string())).unwrap();
+        assert_eq!(first.peer_id(), second.peer_id());
+
+        let wrong = IdentityManager::load_or_generate(&path, Some("wrong".to_string()));
+        assert!(matches!(wrong, Err(IdentityError::WrongPassphrase)));
+
+        let missing = IdentityManager::load_or_generate(&path, None);
+        assert!(matches!(missing, Err(IdentityError::PassphraseRequired)));
+    }
+
+    #[test]
+    fn corrupted_key_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.key");
+        std::fs::write(&path, b"garbage").unwrap();
+
+        let res = IdentityManager::load_or_generate(&path, None);
+        assert!(matches!(res, Err(IdentityError::CorruptedKeyFile)));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn key_file_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.key");
+        IdentityManager::load_or_generate(&path, None).unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn export_and_rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.key");
+        let export_path = dir.path().join("exported.key");
+
+        let mut identity = IdentityManager::load_or_generate(&path, None).unwrap();
+        let old_peer_id = identity.peer_id();
+
+        identity.export(&export_path).unwrap();
+        let exported = IdentityManager::load_or_generate(&export_path, None).unwrap();
+        assert_eq!(exported.peer_id(), old_peer_id);
+
+        let new_peer_id = identity.rotate().unwrap();
+        assert_ne!(new_peer_id, old_peer_id);
+
+        let reloaded = IdentityManager::load_or_generate(&path, None).unwrap();
+        assert_eq!(reloaded.peer_id(), new_peer_id);
+
+        let backup =
+            IdentityManager::load_or_generate(dir.path().join("identity.key.old"), None).unwrap();
+        assert_eq!(backup.peer_id(), old_peer_id);
+
+        // a second rotation keeps the first backup
+        identity.rotate().unwrap();
+        let first_backup =
+            IdentityManager::load_or_generate(dir.path().join("identity.key.old"), None).unwrap();
+        let second_backup =
+            IdentityManager::load_or_generate(dir.path().join("identity.key.old.1"), None).unwrap();
+        assert_eq!(first_backup.peer_id(), old_peer_id);
+        assert_eq!(second_backup.peer_id(), new_peer_id);
+    }
+
+    #[test]
+    fn failed_rotation_keeps_key_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.key");
+        let mut identity = IdentityManager::load_or_generate(&path, None).unwrap();
+
+        // the temporary key file can't be created where a directory is
+        let temp_path = format!("identity.key.tmp-{}", std::process::id());
+        std::fs::create_dir(dir.path().join(temp_path)).unwrap();
+        assert!(identity.rotate().is_err());
+
+        let reloaded = IdentityManager::load_or_generate(&path, None).unwrap();
+        assert_eq!(reloaded.peer_id(), identity.peer_id());
+    }
+
+    #[test]
+    fn passphrase_encrypts_plain_key_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.key");
+
+        let plain = IdentityManager::load_or_generate(&path, None).unwrap();
+        let protected =
+            IdentityManager::load_or_generate(&path, Some("secret".to_string())).unwrap();
+        assert_eq!(plain.peer_id(), protected.peer_id());
+        assert!(protected.is_encrypted());
+
+        let missing = IdentityManager::load_or_generate(&path, None);
+        assert!(matches!(missing, Err(IdentityError::PassphraseRequired)));
+    }
+}
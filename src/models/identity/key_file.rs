@@ -0,0 +1,128 @@
+use crate::models::common::errors::IdentityError;
+use crate::traits::decode::Decode;
+use crate::traits::encode::Encode;
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// On-disk representation of the node keypair.
+/// `key` holds the protobuf encoded keypair, encrypted if `encryption` is set.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KeyFile {
+    encryption: Option<Encryption>,
+    key: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Encryption {
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+}
+
+impl KeyFile {
+    pub fn seal(key: Vec<u8>, passphrase: Option<&str>) -> Result<Self, IdentityError> {
+        let Some(passphrase) = passphrase else {
+            return Ok(Self {
+                encryption: None,
+                key,
+            });
+        };
+
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let cipher = cipher(passphrase, &salt)?;
+        let key = cipher
+            .encrypt(Nonce::from_slice(&nonce), key.as_slice())
+            .map_err(|_| IdentityError::KeyDerivation)?;
+
+        Ok(Self {
+            encryption: Some(Encryption { salt, nonce }),
+            key,
+        })
+    }
+
+    pub fn open(self, passphrase: Option<&str>) -> Result<Vec<u8>, IdentityError> {
+        let Some(encryption) = self.encryption else {
+            return Ok(self.key);
+        };
+
+        let passphrase = passphrase.ok_or(IdentityError::PassphraseRequired)?;
+
+        cipher(passphrase, &encryption.salt)?
+            .decrypt(Nonce::from_slice(&encryption.nonce), self.key.as_slice())
+            .map_err(|_| IdentityError::WrongPassphrase)
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption.is_some()
+    }
+
+    pub fn read(path: &Path) -> Result<Self, IdentityError> {
+        let data = std::fs::read(path).map_err(IdentityError::CouldntReadKeyFile)?;
+
+        Self::decode(&mut data.as_slice()).map_err(|_| IdentityError::CorruptedKeyFile)
+    }
+
+    /// Writes the key file readable by the owner only. The data goes to a temporary file
+    /// next to `path` first, which replaces it once synced, so a failed write keeps the old key
+    pub fn write(&self, path: &Path) -> Result<(), IdentityError> {
+        let data = self
+            .encode_to_vec()
+            .map_err(|_| IdentityError::CorruptedKeyFile)?;
+
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(format!(".tmp-{}", std::process::id()));
+        let temp_path = PathBuf::from(temp_path);
+
+        let result = write_synced(&temp_path, &data)
+            .and_then(|()| std::fs::rename(&temp_path, path))
+            .map_err(IdentityError::CouldntWriteKeyFile);
+        if result.is_err() {
+            let _ = std::fs::remove_file(&temp_path);
+        }
+        result
+    }
+}
+
+/// Creates the file readable by the owner only and waits until `data` is on disk
+fn write_synced(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    file.write_all(data)?;
+    file.sync_all()
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, IdentityError> {
+    let mut key = [0u8; 32];
+
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|_| IdentityError::KeyDerivation)?;
+
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
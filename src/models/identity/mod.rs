@@ -0,0 +1,2 @@
+pub mod identity_manager;
+mod key_file;
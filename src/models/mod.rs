@@ -1,5 +1,14 @@
-pub mod app;
-mod args;
+#[cfg(feature = "cli")]
 pub mod client;
+pub mod command_line;
 pub mod common;
+#[cfg(all(unix, feature = "cli"))]
+pub mod control;
+pub mod history;
+pub mod identity;
+pub mod node;
+#[cfg(feature = "cli")]
+pub mod repl;
 pub mod swarm;
+#[cfg(feature = "cli")]
+pub mod tui;
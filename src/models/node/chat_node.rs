@@ -0,0 +1,205 @@
+use crate::models::common::command::Command;
+use crate::models::common::notification::Notification;
+use crate::models::common::short_peer_id::DEFAULT_SHORT_ID_LEN;
+use crate::models::history::history_store::HistoryStore;
+use crate::models::node::handle::{NodeEvents, NodeHandle};
+use crate::models::swarm::swarm_manager::{SwarmConfig, SwarmManager};
+use libp2p::Multiaddr;
+use libp2p::identity::Keypair;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+pub const DEFAULT_BUFFER_SIZE: usize = 30;
+pub const DEFAULT_DOWNLOAD_DIR: &str = "./downloads";
+
+/// Chat node to embed in other programs, configured with `with_*` methods:
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// use p2p_chat::ChatNode;
+/// use libp2p::identity::Keypair;
+///
+/// let node = ChatNode::new(Keypair::generate_ed25519())
+///     .with_mdns(false)
+///     .start()?;
+///
+/// node.handle.subscribe("ops", None).await?;
+/// node.handle.publish(Some("ops"), "hello").await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ChatNode {
+    keypair: Keypair,
+    listen_addrs: Vec<Multiaddr>,
+    peers: Vec<Multiaddr>,
+    swarm: SwarmConfig,
+    /// capacity of the command and event channels
+    buffer_size: usize,
+    /// history is kept in memory if None
+    data_dir: Option<PathBuf>,
+    download_dir: PathBuf,
+    nick: Option<String>,
+    short_id_len: usize,
+}
+
+/// Started node: the handle to control it, its events and the task driving the swarm,
+/// which ends after `NodeHandle::shutdown`
+pub struct RunningNode {
+    pub handle: NodeHandle,
+    pub events: NodeEvents,
+    pub task: JoinHandle<()>,
+}
+
+impl ChatNode {
+    pub fn new(keypair: Keypair) -> Self {
+        Self {
+            keypair,
+            listen_addrs: Vec::new(),
+            peers: Vec::new(),
+            swarm: SwarmConfig::default(),
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            data_dir: None,
+            download_dir: PathBuf::from(DEFAULT_DOWNLOAD_DIR),
+            nick: None,
+            short_id_len: DEFAULT_SHORT_ID_LEN,
+        }
+    }
+
+    /// Addresses to listen on, any interface with a random port if empty
+    pub fn with_listen_addrs(mut self, addrs: impl IntoIterator<Item = Multiaddr>) -> Self {
+        self.listen_addrs.extend(addrs);
+        self
+    }
+
+    /// Peers dialed on start and redialed whenever the connection drops
+    pub fn with_peers(mut self, addrs: impl IntoIterator<Item = Multiaddr>) -> Self {
+        self.peers.extend(addrs);
+        self
+    }
+
+    /// Finds peers in the local network, enabled by default
+    pub fn with_mdns(mut self, enabled: bool) -> Self {
+        self.swarm.mdns = enabled;
+        self
+    }
+
+    /// How often gossipsub maintains the mesh and gossips, 10s by default
+    pub fn with_heartbeat_interval(mut self, interval: Duration) -> Self {
+        self.swarm.heartbeat_interval = interval;
+        self
+    }
+
+    /// Commands and events queued before senders wait, the node itself waits
+    /// when the events aren't read
+    pub fn with_buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size;
+        self
+    }
+
+    /// Directory with the message history database
+    pub fn with_data_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.data_dir = Some(dir.into());
+        self
+    }
+
+    /// Directory downloaded files are saved to, created on start
+    pub fn with_download_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.download_dir = dir.into();
+        self
+    }
+
+    pub fn with_nick(mut self, nick: Option<String>) -> Self {
+        self.nick = nick;
+        self
+    }
+
+    /// Minimal length of peer ids shown in peer names
+    pub fn with_short_id_len(mut self, len: usize) -> Self {
+        self.short_id_len = len;
+        self
+    }
+
+    /// Starts the swarm on the current tokio runtime
+    pub fn start(self) -> anyhow::Result<RunningNode> {
+        let peer_id = self.keypair.public().to_peer_id();
+
+        let history = match &self.data_dir {
+            Some(dir) => HistoryStore::open(dir)?,
+            None => HistoryStore::in_memory()?,
+        };
+
+        if let Err(e) = std::fs::create_dir_all(&self.download_dir) {
+            log::error!("Couldn't create download dir: {e}");
+        }
+
+        let (command_sender, command_receiver) = mpsc::channel::<Command>(self.buffer_size);
+        let (notification_sender, notification_receiver) =
+            mpsc::channel::<Notification>(self.buffer_size);
+
+        let mut swarm_manager = SwarmManager::build(
+            self.keypair,
+            history,
+            self.listen_addrs,
+            self.swarm,
+            notification_sender,
+            command_receiver,
+        )?
+        .with_peers(self.peers)
+        .with_nick(self.nick)
+        .with_short_id_len(self.short_id_len);
+
+        let task = tokio::spawn(async move {
+            swarm_manager.run().await;
+        });
+
+        Ok(RunningNode {
+            handle: NodeHandle::new(command_sender, peer_id, self.download_dir),
+            events: NodeEvents::new(notification_receiver),
+            task,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::node::test_support::{first_listen_addr, start_local_node};
+
+    #[tokio::test]
+    async fn message_published_through_handle_is_received() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut receiver = start_local_node(&dir.path().join("receiver"), Vec::new());
+        receiver.handle.subscribe("ops", None).await.unwrap();
+
+        let addr = first_listen_addr(&receiver.handle).await;
+
+        let RunningNode {
+            handle, mut events, ..
+        } = start_local_node(&dir.path().join("sender"), vec![addr]);
+        tokio::spawn(async move { while events.recv().await.is_some() {} });
+
+        handle.subscribe("ops", None).await.unwrap();
+        handle
+            .wait_for_topic_peers("ops", 1, Duration::from_secs(10))
+            .await
+            .unwrap();
+        handle.publish(Some("ops"), "hello").await.unwrap();
+
+        let received = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let Some(Notification::Message(received)) = receiver.events.recv().await {
+                    break received;
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(received.topic, "ops");
+        assert_eq!(received.msg.data.as_deref(), Some("hello"));
+        assert_eq!(received.sender_id, handle.peer_id());
+    }
+}
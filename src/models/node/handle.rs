@@ -0,0 +1,286 @@
+use crate::models::common::command::Command;
+use crate::models::common::errors::NodeError;
+use crate::models::common::file::File;
+use crate::models::common::message::Message;
+use crate::models::common::notification::Notification;
+use crate::models::common::response::{
+    AddressList, ConnectOutcome, DirectMessageOutcome, NickOutcome, PeerInfo, SendOutcome,
+    SubscribeOutcome, SwitchOutcome, UnsubscribeOutcome,
+};
+use crate::models::common::topic_key::TopicKey;
+use crate::models::history::history_store::HistoryRecord;
+use libp2p::futures::Stream;
+use libp2p::{Multiaddr, PeerId};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+const PEER_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Controls a running node, clones control the same node
+#[derive(Debug, Clone)]
+pub struct NodeHandle {
+    command_sender: mpsc::Sender<Command>,
+    peer_id: PeerId,
+    download_dir: PathBuf,
+}
+
+/// Messages, peer changes and download progress of a running node.
+/// Events have to be read: once the buffer of `ChatNode::with_buffer_size` is full the node
+/// waits for a free slot and stops handling commands and the network meanwhile.
+/// Drop NodeEvents to discard events instead
+#[derive(Debug)]
+pub struct NodeEvents {
+    notification_receiver: mpsc::Receiver<Notification>,
+}
+
+impl NodeHandle {
+    pub(crate) fn new(
+        command_sender: mpsc::Sender<Command>,
+        peer_id: PeerId,
+        download_dir: PathBuf,
+    ) -> Self {
+        Self {
+            command_sender,
+            peer_id,
+            download_dir,
+        }
+    }
+
+    pub fn peer_id(&self) -> PeerId {
+        self.peer_id
+    }
+
+    pub fn download_dir(&self) -> &Path {
+        &self.download_dir
+    }
+
+    /// Subscribes the topic and makes it active, messages are encrypted with `key` if set
+    pub async fn subscribe(
+        &self,
+        topic: &str,
+        key: Option<TopicKey>,
+    ) -> Result<SubscribeOutcome, NodeError> {
+        Ok(self
+            .request(Command::new_subscribe(topic.to_string(), key))
+            .await??)
+    }
+
+    pub async fn unsubscribe(&self, topic: &str) -> Result<UnsubscribeOutcome, NodeError> {
+        Ok(self
+            .request(Command::new_unsubscribe(topic.to_string()))
+            .await??)
+    }
+
+    /// Makes the topic active, so messages without a topic go there
+    pub async fn switch(&self, topic: &str) -> Result<SwitchOutcome, NodeError> {
+        Ok(self
+            .request(Command::new_switch(topic.to_string()))
+            .await??)
+    }
+
+    /// Publishes the text to the topic or to the active topic if None
+    pub async fn publish(&self, topic: Option<&str>, text: &str) -> Result<SendOutcome, NodeError> {
+        let msg = Message::build(Some(text.to_string()), None).await;
+
+        self.send_message(topic, msg).await
+    }
+
+    /// Offers the file to the topic, peers download it from this node
+    pub async fn send_file(
+        &self,
+        topic: Option<&str>,
+        path: impl AsRef<Path> + Clone,
+        text: Option<String>,
+    ) -> Result<SendOutcome, NodeError> {
+        let file = File::from(path).await?;
+        let msg = Message::build(text, Some(file)).await;
+
+        self.send_message(topic, msg).await
+    }
+
+    /// Sends the text to the peer, returns once the peer confirms delivery
+    pub async fn direct_message(
+        &self,
+        peer: &str,
+        text: &str,
+    ) -> Result<DirectMessageOutcome, NodeError> {
+        let msg = Message::build(Some(text.to_string()), None).await;
+
+        Ok(self
+            .request(Command::new_direct_message(peer.to_string(), msg))
+            .await??)
+    }
+
+    /// Last `limit` messages of the topic, oldest first
+    pub async fn history(
+        &self,
+        topic: &str,
+        limit: usize,
+    ) -> Result<Vec<HistoryRecord>, NodeError> {
+        Ok(self
+            .request(Command::new_history(topic.to_string(), limit))
+            .await??)
+    }
+
+    pub async fn search(&self, text: &str) -> Result<Vec<HistoryRecord>, NodeError> {
+        Ok(self
+            .request(Command::new_search(text.to_string()))
+            .await??)
+    }
+
+    /// Starts downloading the offered file into the download dir, progress comes as events
+    pub async fn download(&self, file_id: &str) -> Result<File, NodeError> {
+        let command = Command::new_download(file_id.to_string(), self.download_dir.clone());
+
+        Ok(self.request(command).await??)
+    }
+
+    /// Dials the address now and whenever the connection drops
+    pub async fn connect(&self, addr: Multiaddr) -> Result<ConnectOutcome, NodeError> {
+        self.request(Command::new_connect(addr)).await
+    }
+
+    pub async fn peers(&self) -> Result<Vec<PeerInfo>, NodeError> {
+        self.request(Command::new_peers()).await
+    }
+
+    /// Connected peers subscribed to the topic, a published message goes to all of them
+    pub async fn topic_peers(&self, topic: &str) -> Result<usize, NodeError> {
+        Ok(self
+            .request(Command::new_topic_peers(topic.to_string()))
+            .await??)
+    }
+
+    /// Waits until at least `min` connected peers are subscribed to the topic,
+    /// publishing earlier fails with `InsufficientPeers`
+    pub async fn wait_for_topic_peers(
+        &self,
+        topic: &str,
+        min: usize,
+        timeout: Duration,
+    ) -> Result<usize, NodeError> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let found = self.topic_peers(topic).await?;
+
+            if found >= min {
+                return Ok(found);
+            }
+
+            if Instant::now() >= deadline {
+                return Err(NodeError::NotEnoughPeers {
+                    topic: topic.to_string(),
+                    found,
+                    wanted: min,
+                });
+            }
+
+            tokio::time::sleep(PEER_POLL_INTERVAL).await;
+        }
+    }
+
+    pub async fn addrs(&self) -> Result<AddressList, NodeError> {
+        self.request(Command::new_addrs()).await
+    }
+
+    /// Announces the nickname to peers, returns the current one if None
+    pub async fn nick(&self, nick: Option<String>) -> Result<NickOutcome, NodeError> {
+        Ok(self.request(Command::new_nick(nick)).await??)
+    }
+
+    /// Leaves topics, closes connections and stops the node
+    pub async fn shutdown(&self) -> Result<(), NodeError> {
+        self.request(Command::new_shutdown()).await
+    }
+
+    /// Sends the command without waiting for its response
+    pub async fn send<T>(
+        &self,
+        (command, response_receiver): (Command, oneshot::Receiver<T>),
+    ) -> Result<oneshot::Receiver<T>, NodeError> {
+        self.command_sender
+            .send(command)
+            .await
+            .map_err(|_| NodeError::Stopped)?;
+
+        Ok(response_receiver)
+    }
+
+    async fn send_message(
+        &self,
+        topic: Option<&str>,
+        msg: Message,
+    ) -> Result<SendOutcome, NodeError> {
+        let command = Command::new_send_message(topic.map(str::to_string), msg);
+
+        Ok(self.request(command).await??)
+    }
+
+    async fn request<T>(&self, command: (Command, oneshot::Receiver<T>)) -> Result<T, NodeError> {
+        self.send(command)
+            .await?
+            .await
+            .map_err(|_| NodeError::Stopped)
+    }
+}
+
+impl NodeEvents {
+    pub(crate) fn new(notification_receiver: mpsc::Receiver<Notification>) -> Self {
+        Self {
+            notification_receiver,
+        }
+    }
+
+    /// Next event, None once the node is stopped
+    pub async fn recv(&mut self) -> Option<Notification> {
+        self.notification_receiver.recv().await
+    }
+}
+
+impl Stream for NodeEvents {
+    type Item = Notification;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.notification_receiver.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::node::test_support::start_local_node;
+
+    #[tokio::test]
+    async fn wait_for_topic_peers_times_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = start_local_node(dir.path(), Vec::new());
+        node.handle.subscribe("ops", None).await.unwrap();
+
+        let started = Instant::now();
+        let res = node
+            .handle
+            .wait_for_topic_peers("ops", 1, Duration::from_millis(300))
+            .await;
+
+        assert!(matches!(
+            res,
+            Err(NodeError::NotEnoughPeers {
+                found: 0,
+                wanted: 1,
+                ..
+            })
+        ));
+        assert!(started.elapsed() >= Duration::from_millis(300));
+
+        let found = node
+            .handle
+            .wait_for_topic_peers("ops", 0, Duration::ZERO)
+            .await;
+        assert_eq!(found.unwrap(), 0);
+    }
+}
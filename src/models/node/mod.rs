@@ -0,0 +1,4 @@
+pub mod chat_node;
+pub mod handle;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
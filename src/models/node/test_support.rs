@@ -0,0 +1,32 @@
+//! Nodes for tests that only talk to each other over the loopback interface
+
+use crate::models::node::chat_node::{ChatNode, RunningNode};
+use crate::models::node::handle::NodeHandle;
+use libp2p::Multiaddr;
+use libp2p::identity::Keypair;
+use std::path::Path;
+use std::time::Duration;
+
+/// Node without mDNS on a random loopback port, keeping history and downloads in `dir`
+pub fn local_node(dir: &Path) -> ChatNode {
+    ChatNode::new(Keypair::generate_ed25519())
+        .with_mdns(false)
+        .with_listen_addrs(["/ip4/127.0.0.1/tcp/0".parse().unwrap()])
+        .with_data_dir(dir.join("data"))
+        .with_download_dir(dir.join("downloads"))
+}
+
+/// Starts a [`local_node`] dialing `peers`
+pub fn start_local_node(dir: &Path, peers: Vec<Multiaddr>) -> RunningNode {
+    local_node(dir).with_peers(peers).start().unwrap()
+}
+
+/// Waits until the node listens and returns its first address
+pub async fn first_listen_addr(handle: &NodeHandle) -> Multiaddr {
+    loop {
+        match handle.addrs().await.unwrap().listen.first() {
+            Some(addr) => return addr.clone(),
+            None => tokio::time::sleep(Duration::from_millis(50)).await,
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use crate::models::client::tracker::{CommandId, Response};
+use crate::models::command_line::parser::{self, COMMANDS};
+use crate::models::common::notification::Notification;
+use crate::models::common::peer_name::PeerName;
+use rustyline::Helper;
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+
+const IDENTITY_COMMANDS: [&str; 3] = ["show", "export", "rotate"];
+
+/// Names the user can complete, updated from command responses and notifications
+#[derive(Debug, Default)]
+pub struct Completions {
+    topics: BTreeSet<String>,
+    peers: Vec<PeerName>,
+    /// topics of subscribe (true) and unsubscribe (false) commands waiting for the node
+    pending: HashMap<CommandId, (String, bool)>,
+}
+
+/// Connects Completions to the line editor
+pub struct ChatHelper {
+    completions: Arc<Mutex<Completions>>,
+    paths: FilenameCompleter,
+}
+
+impl Completions {
+    /// Completes the topic once the node confirms the subscribe command
+    pub fn on_subscribe(&mut self, id: CommandId, topic: String) {
+        self.pending.insert(id, (topic, true));
+    }
+
+    /// Stops completing the topic once the node confirms the unsubscribe command
+    pub fn on_unsubscribe(&mut self, id: CommandId, topic: String) {
+        self.pending.insert(id, (topic, false));
+    }
+
+    pub fn on_response(&mut self, response: &Response) {
+        match self.pending.remove(&response.id) {
+            Some(_) if response.failed => {}
+            Some((topic, true)) => {
+                self.topics.insert(topic);
+            }
+            Some((topic, false)) => {
+                self.topics.remove(&topic);
+            }
+            None => {}
+        }
+    }
+
+    pub fn on_notification(&mut self, notification: &Notification) {
+        match notification {
+            Notification::PeerJoined { name, .. } => self.peers.push(name.clone()),
+            Notification::PeerLeft { name, .. } => self.peers.retain(|peer| peer != name),
+            Notification::NickChanged { old, new, .. } => {
+                if let Some(peer) = self.peers.iter_mut().find(|peer| **peer == *old) {
+                    *peer = new.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Start of the completed word in `line` and the candidates, `line` ends at the cursor
+    pub fn complete(&self, line: &str) -> (usize, Vec<String>) {
+        let start = line.rfind(' ').map_or(0, |i| i + 1);
+        let word = &line[start..];
+        let args: Vec<&str> = line[..start].split_whitespace().collect();
+
+        // only the first argument of a command is completed
+        let command = match args.as_slice() {
+            [] => None,
+            [name] => command_name(name),
+            _ => return (start, Vec::new()),
+        };
+
+        let candidates: Vec<String> = match (command, args.is_empty()) {
+            (_, true) => COMMANDS
+                .iter()
+                .map(|spec| format!("/{}", spec.name))
+                .collect(),
+            (Some("part" | "switch" | "history"), _) => self.topics.iter().cloned().collect(),
+            (Some("msg"), _) => self
+                .topics
+                .iter()
+                .map(|topic| format!("#{topic}"))
+                .collect(),
+            (Some("dm"), _) => self
+                .peers
+                .iter()
+                .filter_map(PeerName::nick)
+                // the dm peer ends at the first space
+                .filter(|nick| !nick.contains(char::is_whitespace))
+                .map(str::to_string)
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+            (Some("identity"), _) => IDENTITY_COMMANDS
+                .iter()
+                .map(|cmd| cmd.to_string())
+                .collect(),
+            (Some("help"), _) => COMMANDS.iter().map(|spec| spec.name.to_string()).collect(),
+            _ => Vec::new(),
+        };
+
+        let candidates = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(word))
+            .collect();
+
+        (start, candidates)
+    }
+}
+
+/// True if the cursor is in a path argument
+pub fn completes_path(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+
+    match words.next().and_then(command_name) {
+        Some("file") => true,
+        Some("identity") => words.next() == Some("export"),
+        Some("join") => line.contains(" --key-file "),
+        _ => false,
+    }
+}
+
+/// Canonical name of the command typed with `/` and maybe an alias
+fn command_name(word: &str) -> Option<&'static str> {
+    word.strip_prefix('/')
+        .and_then(parser::find)
+        .map(|spec| spec.name)
+}
+
+impl ChatHelper {
+    pub fn new(completions: Arc<Mutex<Completions>>) -> Self {
+        Self {
+            completions,
+            paths: FilenameCompleter::new(),
+        }
+    }
+}
+
+impl Completer for ChatHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let line = &line[..pos];
+
+        if completes_path(line) {
+            return self.paths.complete_path(line, pos);
+        }
+
+        let Ok(completions) = self.completions.lock() else {
+            return Ok((pos, Vec::new()));
+        };
+
+        let (start, candidates) = completions.complete(line);
+        let candidates = candidates
+            .into_iter()
+            .map(|candidate| Pair {
+                display: candidate.clone(),
+                replacement: candidate,
+            })
+            .collect();
+
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ChatHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ChatHelper {}
+
+impl Validator for ChatHelper {}
+
+impl Helper for ChatHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::PeerId;
+
+    fn peer(nick: &str) -> PeerName {
+        PeerName::new("x7Fq9a".parse().unwrap(), Some(nick))
+    }
+
+    fn response(id: CommandId, failed: bool) -> Response {
+        Response {
+            id,
+            command: String::new(),
+            lines: Vec::new(),
+            result: serde_json::Value::Null,
+            failed,
+            late: false,
+        }
+    }
+
+    #[test]
+    fn commands_and_topics() {
+        let mut completions = Completions::default();
+        completions.on_subscribe(1, "ops".to_string());
+        completions.on_subscribe(2, "dev".to_string());
+        completions.on_subscribe(3, "secret".to_string());
+        completions.on_subscribe(4, "typo".to_string());
+        completions.on_unsubscribe(5, "dev".to_string());
+
+        for id in 1..=3 {
+            completions.on_response(&response(id, false));
+        }
+        completions.on_response(&response(4, true));
+        completions.on_response(&response(5, false));
+
+        assert_eq!(completions.complete("/jo"), (0, vec!["/join".to_string()]));
+        assert_eq!(
+            completions.complete("/switch "),
+            (8, vec!["ops".to_string(), "secret".to_string()])
+        );
+        assert_eq!(
+            completions.complete("/leave o"),
+            (7, vec!["ops".to_string()])
+        );
+        assert_eq!(completions.complete("/m #o"), (3, vec!["#ops".to_string()]));
+        assert_eq!(
+            completions.complete("/identity r"),
+            (10, vec!["rotate".to_string()])
+        );
+        assert_eq!(
+            completions.complete("/help pe"),
+            (6, vec!["peers".to_string()])
+        );
+        assert!(completions.complete("/msg #ops hel").1.is_empty());
+        assert!(completions.complete("hello wor").1.is_empty());
+    }
+
+    #[test]
+    fn nicks_follow_notifications() {
+        let mut completions = Completions::default();
+
+        completions.on_notification(&Notification::PeerJoined {
+            name: peer("alice"),
+            peer_id: PeerId::random(),
+        });
+        completions.on_notification(&Notification::PeerJoined {
+            name: peer("bob"),
+            peer_id: PeerId::random(),
+        });
+        completions.on_notification(&Notification::PeerJoined {
+            name: peer("two words"),
+            peer_id: PeerId::random(),
+        });
+        completions.on_notification(&Notification::NickChanged {
+            peer_id: PeerId::random(),
+            old: peer("bob"),
+            new: peer("bobby"),
+            conflicts: Vec::new(),
+        });
+        completions.on_notification(&Notification::PeerLeft {
+            name: peer("alice"),
+            peer_id: PeerId::random(),
+        });
+
+        assert_eq!(completions.complete("/dm "), (4, vec!["bobby".to_string()]));
+    }
+
+    #[test]
+    fn path_arguments() {
+        assert!(completes_path("/file ./rep"));
+        assert!(completes_path("/identity export /tmp/"));
+        assert!(completes_path("/subscribe ops --key-file ~/"));
+        assert!(!completes_path("/identity show"));
+        assert!(!completes_path("/filesystem"));
+        assert!(!completes_path("file "));
+    }
+}
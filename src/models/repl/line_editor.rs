@@ -0,0 +1,265 @@
+use crate::models::client::signals::Signals;
+use crate::models::client::tracker::Tracked;
+use crate::models::client::{Exit, Output, Session, available_commands, format_error};
+use crate::models::command_line::parser::{self, UserCommand};
+use crate::models::command_line::tokenizer;
+use crate::models::common::errors::CommandLineError;
+use crate::models::node::handle::NodeEvents;
+use crate::models::repl::completion::{ChatHelper, Completions};
+use rustyline::error::ReadlineError;
+use rustyline::history::FileHistory;
+use rustyline::{Config, Editor, ExternalPrinter};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+const PROMPT: &str = "chat> ";
+const MAX_INPUT_HISTORY: usize = 1000;
+
+type ChatEditor = Editor<ChatHelper, FileHistory>;
+
+/// Line mode client with editing, history and completion.
+/// The editor blocks, so it runs on its own thread and sends entered lines here
+pub struct Repl {
+    session: Session,
+    events: NodeEvents,
+    /// file with entered lines, kept between runs
+    history_path: Option<PathBuf>,
+}
+
+/// What the editor thread reads
+enum Input {
+    Line(String),
+    /// Ctrl-C, raw mode of the editor turns it into a key instead of SIGINT
+    Interrupted,
+}
+
+/// Prints above the prompt when stdin is a terminal, to stdout otherwise
+#[derive(Clone)]
+struct Printer(Option<Arc<Mutex<Box<dyn ExternalPrinter + Send>>>>);
+
+impl Repl {
+    pub fn new(session: Session, events: NodeEvents, history_path: Option<PathBuf>) -> Self {
+        Self {
+            session,
+            events,
+            history_path,
+        }
+    }
+
+    /// Runs until the user quits, stdin is closed or a signal comes
+    pub async fn run(mut self, mut signals: Signals) -> rustyline::Result<Exit> {
+        let completions = Arc::new(Mutex::new(Completions::default()));
+
+        let config = Config::builder()
+            .max_history_size(MAX_INPUT_HISTORY)?
+            .auto_add_history(false)
+            .build();
+        let mut editor = ChatEditor::with_config(config)?;
+        editor.set_helper(Some(ChatHelper::new(completions.clone())));
+
+        if let Some(path) = &self.history_path
+            && path.exists()
+            && let Err(e) = editor.load_history(path)
+        {
+            log::warn!("Couldn't load input history: {e}");
+        }
+
+        let printer = Printer::new(&mut editor);
+        let output = self.session.output();
+
+        if output == Output::Text {
+            printer.print(vec!["----------------------------------".to_string()]);
+            printer.print(available_commands());
+            printer.print(vec!["----------------------------------".to_string()]);
+        }
+
+        let (input_sender, mut input_receiver) = mpsc::channel::<Input>(1);
+        let history_path = self.history_path.clone();
+        tokio::task::spawn_blocking(move || read_lines(editor, history_path, input_sender));
+
+        let exit = loop {
+            tokio::select! {
+                input = input_receiver.recv() => match input {
+                    Some(Input::Line(line)) => {
+                        let lines = match parser::parse(&line) {
+                            Ok(UserCommand::Quit) => break Exit::Quit,
+                            Ok(cmd) => self.execute(cmd, &completions).await,
+                            Err(CommandLineError::Empty) => continue,
+                            Err(e) => output.error(format_error(&e)),
+                        };
+
+                        printer.print(lines);
+                    }
+                    Some(Input::Interrupted) => break Exit::Interrupted,
+                    // stdin is closed
+                    None => break Exit::Quit,
+                },
+                notification = self.events.recv() => match notification {
+                    Some(notification) => {
+                        if let Ok(mut completions) = completions.lock() {
+                            completions.on_notification(&notification);
+                        }
+
+                        printer.print(output.notification(notification));
+                    }
+                    None => break Exit::Quit,
+                },
+                Some(tracked) = self.session.response() => {
+                    if let Tracked::Response(response) = &tracked
+                        && let Ok(mut completions) = completions.lock()
+                    {
+                        completions.on_response(response);
+                    }
+
+                    printer.print(output.tracked(&tracked));
+                }
+                exit = signals.recv() => break exit,
+            }
+        };
+
+        printer.print(output.info(vec!["> Shutting down...".to_string()]));
+
+        Ok(exit)
+    }
+
+    /// Executes the command, topics are completed once the node confirms them
+    async fn execute(&mut self, cmd: UserCommand, completions: &Mutex<Completions>) -> Vec<String> {
+        match cmd {
+            UserCommand::Subscribe(topic, key) => {
+                match self.session.subscribe(topic.clone(), key).await {
+                    Ok(id) => {
+                        if let Ok(mut completions) = completions.lock() {
+                            completions.on_subscribe(id, topic);
+                        }
+
+                        Vec::new()
+                    }
+                    Err(lines) => self.session.output().error(lines),
+                }
+            }
+            UserCommand::Unsubscribe(topic) => {
+                let id = self.session.unsubscribe(topic.clone()).await;
+
+                if let Ok(mut completions) = completions.lock() {
+                    completions.on_unsubscribe(id, topic);
+                }
+
+                Vec::new()
+            }
+            cmd => self.session.execute(cmd).await,
+        }
+    }
+}
+
+fn read_lines(
+    mut editor: ChatEditor,
+    history_path: Option<PathBuf>,
+    input_sender: mpsc::Sender<Input>,
+) {
+    loop {
+        match editor.readline(PROMPT) {
+            Ok(line) => {
+                if remembered(&line) {
+                    let _ = editor.add_history_entry(line.as_str());
+
+                    if let Some(path) = &history_path
+                        && let Err(e) = editor.append_history(path)
+                    {
+                        log::warn!("Couldn't save input history: {e}");
+                    }
+                }
+
+                // the prompt isn't shown again, so the terminal is restored before exit
+                let quit = matches!(parser::parse(&line), Ok(UserCommand::Quit));
+
+                if input_sender.blocking_send(Input::Line(line)).is_err() || quit {
+                    return;
+                }
+            }
+            Err(ReadlineError::Interrupted) => {
+                let _ = input_sender.blocking_send(Input::Interrupted);
+                return;
+            }
+            Err(ReadlineError::Eof) => return,
+            Err(e) => {
+                log::error!("Error while reading input: {e}");
+                return;
+            }
+        }
+    }
+}
+
+/// Blank lines and lines with a topic passphrase aren't written to the history file
+fn remembered(line: &str) -> bool {
+    if line.trim().is_empty() {
+        return false;
+    }
+
+    match tokenizer::words(line) {
+        Some(words) => !words
+            .iter()
+            .any(|word| word == "--key" || word.starts_with("--key=")),
+        // can't tell where the passphrase is
+        None => !line.contains("--key"),
+    }
+}
+
+impl Printer {
+    fn new(editor: &mut ChatEditor) -> Self {
+        match editor.create_external_printer() {
+            Ok(printer) => Self(Some(Arc::new(Mutex::new(Box::new(printer))))),
+            Err(_) => Self(None),
+        }
+    }
+
+    fn print(&self, lines: Vec<String>) {
+        if lines.is_empty() {
+            return;
+        }
+
+        let Some(printer) = &self.0 else {
+            lines.iter().for_each(|line| println!("{line}"));
+            return;
+        };
+
+        let text = lines.join("\n") + "\n";
+
+        match printer.lock() {
+            Ok(mut printer) => {
+                if let Err(e) = printer.print(text) {
+                    log::error!("Couldn't print output: {e}");
+                }
+            }
+            Err(e) => log::error!("Couldn't print output: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passphrases_are_not_remembered() {
+        for line in [
+            "/join ops --key hunter2",
+            "/join ops --key",
+            "/join ops\t--key\thunter2",
+            "/join ops --key=hunter2",
+            "/join 'ops team' --key \"two words\"",
+            "/join ops --key \"unclosed",
+            "   ",
+        ] {
+            assert!(!remembered(line), "line: {line}");
+        }
+
+        for line in [
+            "/join ops",
+            "/join ops --key-file ~/ops.key",
+            "hello --keys",
+        ] {
+            assert!(remembered(line), "line: {line}");
+        }
+    }
+}
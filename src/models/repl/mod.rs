@@ -0,0 +1,2 @@
+pub mod completion;
+pub mod line_editor;
@@ -0,0 +1,76 @@
+use crate::models::common::response::AddressList;
+use libp2p::Multiaddr;
+
+/// Max number of observed addresses kept, older ones are dropped first
+const MAX_OBSERVED: usize = 8;
+
+/// Addresses the local node listens on and the ones other peers see it at
+#[derive(Debug, Default)]
+pub struct Addresses {
+    listen: Vec<Multiaddr>,
+    observed: Vec<Multiaddr>,
+}
+
+impl Addresses {
+    pub fn add_listen(&mut self, addr: Multiaddr) {
+        if !self.listen.contains(&addr) {
+            self.listen.push(addr);
+        }
+    }
+
+    pub fn remove_listen(&mut self, addr: &Multiaddr) {
+        self.listen.retain(|listen| listen != addr);
+    }
+
+    /// Address reported by a remote peer, it may be wrong or behind NAT
+    pub fn add_observed(&mut self, addr: Multiaddr) {
+        self.observed.retain(|observed| observed != &addr);
+        self.observed.push(addr);
+
+        if self.observed.len() > MAX_OBSERVED {
+            self.observed.remove(0);
+        }
+    }
+
+    pub fn list(&self) -> AddressList {
+        AddressList {
+            listen: self.listen.clone(),
+            observed: self.observed.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> Multiaddr {
+        format!("/ip4/10.0.0.1/tcp/{port}").parse().unwrap()
+    }
+
+    #[test]
+    fn listen_addrs_are_added_and_removed() {
+        let mut addresses = Addresses::default();
+
+        addresses.add_listen(addr(1));
+        addresses.add_listen(addr(1));
+        addresses.add_listen(addr(2));
+        addresses.remove_listen(&addr(1));
+
+        assert_eq!(addresses.listen, vec![addr(2)]);
+    }
+
+    #[test]
+    fn observed_keeps_most_recent() {
+        let mut addresses = Addresses::default();
+
+        for port in 0..=MAX_OBSERVED as u16 {
+            addresses.add_observed(addr(port));
+        }
+        addresses.add_observed(addr(1));
+
+        assert_eq!(addresses.observed.len(), MAX_OBSERVED);
+        assert_eq!(addresses.observed.first(), Some(&addr(2)));
+        assert_eq!(addresses.observed.last(), Some(&addr(1)));
+    }
+}
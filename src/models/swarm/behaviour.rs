@@ -1,18 +1,31 @@
+use crate::models::swarm::swarm_manager::SwarmConfig;
+use crate::models::swarm::{direct_message, file_transfer, history_sync, nickname};
 use anyhow::Result;
 use libp2p::identity::Keypair;
 use libp2p::swarm::NetworkBehaviour;
-use libp2p::{gossipsub, mdns};
+use libp2p::swarm::behaviour::toggle::Toggle;
+use libp2p::{gossipsub, identify, mdns, ping};
 use std::hash::{DefaultHasher, Hash, Hasher};
-use std::time::Duration;
+
+const IDENTIFY_PROTOCOL: &str = "/p2p-chat/id/1.0.0";
 
 #[derive(NetworkBehaviour)]
 pub struct ChatBehaviour {
-    pub mdns: mdns::tokio::Behaviour,
+    /// finds peers in the local network, disabled if None
+    pub mdns: Toggle<mdns::tokio::Behaviour>,
     pub gossipsub: gossipsub::Behaviour,
+    pub file_transfer: file_transfer::Behaviour,
+    pub direct_message: direct_message::Behaviour,
+    pub history_sync: history_sync::Behaviour,
+    pub nickname: nickname::Behaviour,
+    /// tells peers the address they are seen at
+    pub identify: identify::Behaviour,
+    /// measures latency to connected peers
+    pub ping: ping::Behaviour,
 }
 
 impl ChatBehaviour {
-    pub fn build(key: &Keypair) -> Result<Self> {
+    pub fn build(key: &Keypair, config: &SwarmConfig) -> Result<Self> {
         let message_id_fn = |message: &gossipsub::Message| {
             let sequence_number = message.sequence_number.unwrap_or(0);
             let peer_id_as_base58 = message
@@ -29,19 +42,40 @@ impl ChatBehaviour {
         };
 
         let gossipsub_config = gossipsub::ConfigBuilder::default()
-            .heartbeat_interval(Duration::from_secs(10))
+            .heartbeat_interval(config.heartbeat_interval)
+            // messages are signed and their source is shown as the sender,
+            // which only strict validation verifies
             .validation_mode(gossipsub::ValidationMode::Strict)
             .message_id_fn(message_id_fn)
             .build()
-            .map_err(|msg| std::io::Error::new(std::io::ErrorKind::Other, msg))?;
+            .map_err(std::io::Error::other)?;
 
         let gossipsub = gossipsub::Behaviour::new(
             gossipsub::MessageAuthenticity::Signed(key.clone()),
             gossipsub_config,
         )
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        .map_err(std::io::Error::other)?;
+
+        let mdns = match config.mdns {
+            true => Some(mdns::tokio::Behaviour::new(
+                mdns::Config::default(),
+                key.public().to_peer_id(),
+            )?),
+            false => None,
+        };
 
-        let mdns = mdns::tokio::Behaviour::new(mdns::Config::default(), key.public().to_peer_id())?;
-        Ok(ChatBehaviour { gossipsub, mdns })
+        Ok(ChatBehaviour {
+            gossipsub,
+            mdns: Toggle::from(mdns),
+            file_transfer: file_transfer::behaviour(),
+            direct_message: direct_message::behaviour(),
+            history_sync: history_sync::behaviour(),
+            nickname: nickname::behaviour(),
+            identify: identify::Behaviour::new(identify::Config::new(
+                IDENTIFY_PROTOCOL.to_string(),
+                key.public(),
+            )),
+            ping: ping::Behaviour::default(),
+        })
     }
 }
@@ -0,0 +1,255 @@
+use libp2p::core::ConnectedPoint;
+use libp2p::multiaddr::Protocol;
+use libp2p::swarm::dial_opts::DialOpts;
+use libp2p::swarm::{ConnectionId, DialError};
+use libp2p::{Multiaddr, PeerId};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct DialTarget {
+    addr: Multiaddr,
+    /// taken from the `/p2p` part of the address or learned on connection
+    peer_id: Option<PeerId>,
+    failures: u32,
+    next_dial: Option<Instant>,
+}
+
+/// Addresses given explicitly by the user or the bootstrap list.
+/// They are dialed on start and redialed with exponential backoff when the connection drops
+#[derive(Debug, Default)]
+pub struct Dialer {
+    targets: Vec<DialTarget>,
+    /// dials in progress by connection id, with the index of their target
+    pending: HashMap<ConnectionId, usize>,
+}
+
+impl Dialer {
+    /// Returns false if the address is already known
+    pub fn add(&mut self, addr: Multiaddr) -> bool {
+        if self.targets.iter().any(|target| target.addr == addr) {
+            return false;
+        }
+
+        let peer_id = addr.iter().find_map(|protocol| match protocol {
+            Protocol::P2p(peer_id) => Some(peer_id),
+            _ => None,
+        });
+
+        self.targets.push(DialTarget {
+            addr,
+            peer_id,
+            failures: 0,
+            next_dial: Some(Instant::now()),
+        });
+
+        true
+    }
+
+    pub fn is_target(&self, peer_id: &PeerId) -> bool {
+        self.targets
+            .iter()
+            .any(|target| target.peer_id.as_ref() == Some(peer_id))
+    }
+
+    /// Dials every target whose dial is due with `dial`.
+    /// Dials rejected right away are rescheduled like ones that fail later
+    pub fn dial_due(
+        &mut self,
+        now: Instant,
+        mut dial: impl FnMut(DialOpts) -> Result<(), DialError>,
+    ) {
+        for opts in self.due(now) {
+            let connection_id = opts.connection_id();
+
+            if let Err(e) = dial(opts) {
+                log::warn!("Couldn't dial peer: {e}");
+                self.on_dial_failed(connection_id);
+            }
+        }
+    }
+
+    /// Dial options for every target whose dial is due
+    fn due(&mut self, now: Instant) -> Vec<DialOpts> {
+        let mut dials = Vec::new();
+
+        for (index, target) in self.targets.iter_mut().enumerate() {
+            if target.next_dial.is_none_or(|next_dial| next_dial > now) {
+                continue;
+            }
+
+            target.next_dial = None;
+
+            let opts = DialOpts::unknown_peer_id()
+                .address(target.addr.clone())
+                .build();
+
+            self.pending.insert(opts.connection_id(), index);
+            dials.push(opts);
+        }
+
+        dials
+    }
+
+    /// Returns true if the connection belongs to one of the targets
+    pub fn on_connected(
+        &mut self,
+        connection_id: ConnectionId,
+        peer_id: PeerId,
+        endpoint: &ConnectedPoint,
+    ) -> bool {
+        let index = self.pending.remove(&connection_id).or_else(|| {
+            // peer may have dialed us first
+            self.targets.iter().position(|target| {
+                target.peer_id == Some(peer_id) || &target.addr == endpoint.get_remote_address()
+            })
+        });
+
+        let Some(target) = index.and_then(|index| self.targets.get_mut(index)) else {
+            return false;
+        };
+
+        target.peer_id = Some(peer_id);
+        target.failures = 0;
+        target.next_dial = None;
+
+        true
+    }
+
+    pub fn on_dial_failed(&mut self, connection_id: ConnectionId) {
+        if let Some(target) = self
+            .pending
+            .remove(&connection_id)
+            .and_then(|index| self.targets.get_mut(index))
+        {
+            target.schedule_redial();
+            log::warn!(
+                "Couldn't dial {}, next attempt in {:?}",
+                target.addr,
+                target.backoff()
+            );
+        }
+    }
+
+    /// Called when the last connection to the peer is closed
+    pub fn on_disconnected(&mut self, peer_id: PeerId) {
+        for target in self
+            .targets
+            .iter_mut()
+            .filter(|target| target.peer_id == Some(peer_id))
+        {
+            target.schedule_redial();
+            log::info!(
+                "Lost connection to {}, redialing in {:?}",
+                target.addr,
+                target.backoff()
+            );
+        }
+    }
+}
+
+impl DialTarget {
+    fn backoff(&self) -> Duration {
+        INITIAL_BACKOFF
+            .saturating_mul(2u32.saturating_pow(self.failures.saturating_sub(1)))
+            .min(MAX_BACKOFF)
+    }
+
+    fn schedule_redial(&mut self) {
+        self.failures += 1;
+        self.next_dial = Some(Instant::now() + self.backoff());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::core::Endpoint;
+    use libp2p::core::transport::PortUse;
+
+    fn addr() -> Multiaddr {
+        "/ip4/10.0.0.1/tcp/4001".parse().unwrap()
+    }
+
+    fn endpoint() -> ConnectedPoint {
+        ConnectedPoint::Dialer {
+            address: addr(),
+            role_override: Endpoint::Dialer,
+            port_use: PortUse::Reuse,
+        }
+    }
+
+    #[test]
+    fn add_ignores_duplicates() {
+        let mut dialer = Dialer::default();
+
+        assert!(dialer.add(addr()));
+        assert!(!dialer.add(addr()));
+
+        assert_eq!(dialer.due(Instant::now()).len(), 1);
+        assert!(dialer.due(Instant::now()).is_empty());
+    }
+
+    #[test]
+    fn failed_dial_backs_off() {
+        let mut dialer = Dialer::default();
+        dialer.add(addr());
+
+        for expected in [1, 2, 4, 8, 16, 32, 60, 60] {
+            let dial = dialer.due(Instant::now() + MAX_BACKOFF).pop().unwrap();
+            dialer.on_dial_failed(dial.connection_id());
+
+            assert_eq!(dialer.targets[0].backoff(), Duration::from_secs(expected));
+        }
+    }
+
+    #[test]
+    fn redial_after_disconnect() {
+        let mut dialer = Dialer::default();
+        let peer_id = PeerId::random();
+        dialer.add(addr());
+
+        let dial = dialer.due(Instant::now()).pop().unwrap();
+        assert!(dialer.on_connected(dial.connection_id(), peer_id, &endpoint()));
+        assert!(dialer.due(Instant::now() + MAX_BACKOFF).is_empty());
+
+        dialer.on_disconnected(peer_id);
+        assert!(dialer.due(Instant::now()).is_empty());
+        assert_eq!(dialer.due(Instant::now() + INITIAL_BACKOFF).len(), 1);
+    }
+
+    #[test]
+    fn rejected_dial_is_rescheduled() {
+        let mut dialer = Dialer::default();
+        dialer.add(addr());
+
+        let mut dials = 0;
+        dialer.dial_due(Instant::now(), |_| {
+            dials += 1;
+            Err(DialError::NoAddresses)
+        });
+        assert_eq!(dials, 1);
+        assert_eq!(dialer.targets[0].failures, 1);
+        assert!(dialer.pending.is_empty());
+
+        dialer.dial_due(Instant::now() + INITIAL_BACKOFF, |_| {
+            dials += 1;
+            Ok(())
+        });
+        assert_eq!(dials, 2);
+    }
+
+    #[test]
+    fn unknown_connection_is_ignored() {
+        let mut dialer = Dialer::default();
+
+        assert!(!dialer.on_connected(
+            ConnectionId::new_unchecked(1),
+            PeerId::random(),
+            &endpoint()
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::models::common::command::respond;
+use crate::models::common::errors::DirectMessageError;
+use crate::models::common::message::Message;
+use crate::models::common::notification::Notification;
+use crate::models::common::response::DirectMessageOutcome;
+use crate::models::swarm::peer_directory::PeerDirectory;
+use jiff::Timestamp;
+use libp2p::request_response::{self, OutboundRequestId, ProtocolSupport};
+use libp2p::{PeerId, StreamProtocol};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use tokio::sync::oneshot;
+
+const PROTOCOL: StreamProtocol = StreamProtocol::new("/p2p-chat/dm/1.0.0");
+
+/// Confirmation that the recipient got the message
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Delivered;
+
+pub type Behaviour = request_response::cbor::Behaviour<Message, Delivered>;
+pub type Event = request_response::Event<Message, Delivered>;
+
+pub fn behaviour() -> Behaviour {
+    Behaviour::new(
+        [(PROTOCOL, ProtocolSupport::Full)],
+        request_response::Config::default(),
+    )
+}
+
+type DeliverySender = oneshot::Sender<Result<DirectMessageOutcome, DirectMessageError>>;
+
+/// Direct messages waiting for delivery confirmation
+#[derive(Default)]
+pub struct DirectMessages {
+    pending: HashMap<OutboundRequestId, (PeerId, DeliverySender)>,
+}
+
+impl DirectMessages {
+    /// Sends the message, `response_sender` gets the result once the peer confirms delivery
+    pub fn send(
+        &mut self,
+        behaviour: &mut Behaviour,
+        peer: PeerId,
+        msg: Message,
+        response_sender: DeliverySender,
+    ) {
+        let request_id = behaviour.send_request(&peer, msg);
+        self.pending.insert(request_id, (peer, response_sender));
+    }
+
+    pub fn handle_event(
+        &mut self,
+        behaviour: &mut Behaviour,
+        directory: &PeerDirectory,
+        event: Event,
+    ) -> Option<Notification> {
+        match event {
+            Event::Message {
+                peer,
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+                ..
+            } => {
+                log::info!("Got direct message: '{request}' from peer: {peer}");
+
+                if behaviour.send_response(channel, Delivered).is_err() {
+                    log::warn!("Couldn't confirm direct message delivery to {peer}");
+                }
+
+                let received = Timestamp::now();
+
+                Some(Notification::DirectMessage {
+                    id: message_id(&peer, received, &request),
+                    sender: directory.name(&peer),
+                    sender_id: peer,
+                    timestamp: received.as_second(),
+                    msg: request,
+                })
+            }
+            Event::Message {
+                message: request_response::Message::Response { request_id, .. },
+                ..
+            } => {
+                let (peer, response_sender) = self.pending.remove(&request_id)?;
+
+                let outcome = DirectMessageOutcome {
+                    recipient: directory.name(&peer),
+                    peer_id: peer,
+                };
+                respond(response_sender, Ok(outcome));
+                None
+            }
+            Event::OutboundFailure {
+                request_id, error, ..
+            } => {
+                let (peer, response_sender) = self.pending.remove(&request_id)?;
+
+                log::warn!("Direct message to {peer} failed: {error}");
+                let error = DirectMessageError::NotDelivered {
+                    peer: directory.name(&peer),
+                    reason: error.to_string(),
+                };
+                respond(response_sender, Err(error));
+                None
+            }
+            Event::InboundFailure { peer, error, .. } => {
+                log::warn!("Failed to receive direct message from {peer}: {error}");
+                None
+            }
+            Event::ResponseSent { .. } => None,
+        }
+    }
+}
+
+/// Id of a received direct message, unique for this node like the ids of topic messages
+fn message_id(peer: &PeerId, received: Timestamp, msg: &Message) -> String {
+    let mut s = DefaultHasher::new();
+    (peer.to_base58(), received.as_nanosecond(), msg.to_string()).hash(&mut s);
+
+    s.finish().to_string()
+}
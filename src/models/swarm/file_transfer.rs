@@ -0,0 +1,453 @@
+use crate::models::common::errors::TransferError;
+use crate::models::common::file::{ContentHasher, File};
+use crate::models::common::notification::Notification;
+use crate::models::swarm::peer_directory::PeerDirectory;
+use libp2p::request_response::{self, InboundRequestId, OutboundRequestId, ProtocolSupport};
+use libp2p::{PeerId, StreamProtocol};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+const PROTOCOL: StreamProtocol = StreamProtocol::new("/p2p-chat/file/1.0.0");
+const CHUNK_SIZE: u32 = 256 * 1024;
+/// Progress is reported every time this share of the file is received
+const PROGRESS_STEP_PERCENT: u64 = 10;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChunkRequest {
+    pub hash: String,
+    pub offset: u64,
+    pub len: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ChunkResponse {
+    Chunk(#[serde(with = "serde_bytes")] Vec<u8>),
+    NotFound,
+}
+
+pub type Behaviour = request_response::cbor::Behaviour<ChunkRequest, ChunkResponse>;
+pub type Event = request_response::Event<ChunkRequest, ChunkResponse>;
+
+pub fn behaviour() -> Behaviour {
+    Behaviour::new(
+        [(PROTOCOL, ProtocolSupport::Full)],
+        request_response::Config::default(),
+    )
+}
+
+struct Download {
+    peer: PeerId,
+    file: File,
+    dir: PathBuf,
+    part_path: PathBuf,
+    writer: tokio::fs::File,
+    received: u64,
+    content: ContentHasher,
+}
+
+/// Files shared by this node and offered by others, and downloads in progress
+#[derive(Default)]
+pub struct FileTransfer {
+    /// local files by hash, served to peers on request
+    shared: HashMap<String, (PathBuf, File)>,
+    /// names of the files whose last chunk is being sent to the peer
+    uploads: HashMap<InboundRequestId, String>,
+    /// files offered by other peers by hash
+    offers: HashMap<String, (PeerId, File)>,
+    /// downloads by the id of their chunk request in flight
+    downloads: HashMap<OutboundRequestId, Download>,
+}
+
+impl FileTransfer {
+    pub fn share(&mut self, file: &File) {
+        if let Some(path) = &file.path {
+            self.shared
+                .insert(file.hash.clone(), (path.clone(), file.clone()));
+        }
+    }
+
+    /// Returns false and ignores the offer if its hash is invalid
+    pub fn add_offer(&mut self, peer: PeerId, file: File) -> bool {
+        if !file.has_valid_hash() {
+            log::warn!("Peer {peer} offered file {} with invalid hash", file.name);
+            return false;
+        }
+
+        self.offers.insert(file.hash.clone(), (peer, file));
+        true
+    }
+
+    /// Stops all downloads and removes their partial files. Downloads don't resume,
+    /// so the received part is lost and the file has to be downloaded again
+    pub async fn cancel_all(&mut self, reason: &str) -> Vec<Notification> {
+        let mut notifications = Vec::new();
+
+        for (_, download) in self.downloads.drain() {
+            notifications.push(fail(download, reason.to_string()).await);
+        }
+
+        notifications
+    }
+
+    /// Starts pulling the offered file whose hash starts with `file_id` into `dir`
+    pub async fn start_download(
+        &mut self,
+        behaviour: &mut Behaviour,
+        file_id: &str,
+        dir: &Path,
+    ) -> Result<File, TransferError> {
+        let (peer, file) = self.find_offer(file_id)?.clone();
+
+        if self.downloads.values().any(|d| d.file.hash == file.hash) {
+            return Err(TransferError::AlreadyDownloading);
+        }
+
+        // fails early if the name is rejected, the final path is picked when download finishes
+        file.safe_name()?;
+
+        let part_path = dir.join(format!(".{}.part", file.hash));
+
+        let writer = tokio::fs::File::create(&part_path)
+            .await
+            .map_err(TransferError::CouldntWriteFile)?;
+
+        let download = Download {
+            peer,
+            file: file.clone(),
+            dir: dir.to_path_buf(),
+            part_path,
+            writer,
+            received: 0,
+            content: ContentHasher::default(),
+        };
+
+        self.request_next_chunk(behaviour, download);
+
+        Ok(file)
+    }
+
+    pub async fn handle_event(
+        &mut self,
+        behaviour: &mut Behaviour,
+        directory: &PeerDirectory,
+        event: Event,
+    ) -> Option<Notification> {
+        match event {
+            Event::Message {
+                peer,
+                message:
+                    request_response::Message::Request {
+                        request_id,
+                        request,
+                        channel,
+                    },
+                ..
+            } => {
+                let response = match self.shared.get(&request.hash) {
+                    Some((path, file)) => match read_chunk(path, request.offset, request.len).await
+                    {
+                        Ok(chunk) => {
+                            if request.offset + chunk.len() as u64 >= file.size {
+                                self.uploads.insert(request_id, file.name.clone());
+                            }
+                            ChunkResponse::Chunk(chunk)
+                        }
+                        Err(e) => {
+                            log::error!("Couldn't read shared file {}: {e}", path.display());
+                            ChunkResponse::NotFound
+                        }
+                    },
+                    None => {
+                        log::warn!("Peer {peer} requested unknown file {}", request.hash);
+                        ChunkResponse::NotFound
+                    }
+                };
+
+                if behaviour.send_response(channel, response).is_err() {
+                    log::warn!("Couldn't send file chunk to {peer}, connection closed");
+                    self.uploads.remove(&request_id);
+                }
+
+                None
+            }
+            Event::Message {
+                message:
+                    request_response::Message::Response {
+                        request_id,
+                        response,
+                    },
+                ..
+            } => {
+                let download = self.downloads.remove(&request_id)?;
+
+                match response {
+                    ChunkResponse::Chunk(chunk) => {
+                        self.handle_chunk(behaviour, download, chunk).await
+                    }
+                    ChunkResponse::NotFound => {
+                        Some(fail(download, "file is not shared anymore".to_string()).await)
+                    }
+                }
+            }
+            Event::OutboundFailure {
+                request_id, error, ..
+            } => {
+                let download = self.downloads.remove(&request_id)?;
+
+                Some(fail(download, error.to_string()).await)
+            }
+            Event::InboundFailure {
+                peer,
+                request_id,
+                error,
+                ..
+            } => {
+                log::warn!("Failed to serve file chunk to {peer}: {error}");
+                self.uploads.remove(&request_id);
+                None
+            }
+            Event::ResponseSent {
+                peer, request_id, ..
+            } => {
+                let name = self.uploads.remove(&request_id)?;
+                log::info!("Peer {peer} got the last chunk of {name}");
+
+                Some(Notification::UploadFinished {
+                    name,
+                    peer: directory.name(&peer),
+                    peer_id: peer,
+                })
+            }
+        }
+    }
+
+    async fn handle_chunk(
+        &mut self,
+        behaviour: &mut Behaviour,
+        mut download: Download,
+        chunk: Vec<u8>,
+    ) -> Option<Notification> {
+        let remaining = download.file.size - download.received;
+
+        if chunk.len() as u64 > remaining || (chunk.is_empty() && remaining > 0) {
+            return Some(fail(download, "sender returned unexpected data".to_string()).await);
+        }
+
+        if let Err(e) = download.writer.write_all(&chunk).await {
+            return Some(fail(download, e.to_string()).await);
+        }
+
+        download.content.update(&chunk);
+
+        let previous = download.received;
+        download.received += chunk.len() as u64;
+
+        if download.received == download.file.size {
+            return Some(finish(download).await);
+        }
+
+        let size = download.file.size;
+        let step = (size * PROGRESS_STEP_PERCENT / 100).max(1);
+        let notification =
+            (previous / step != download.received / step).then(|| Notification::DownloadProgress {
+                name: download.file.name.clone(),
+                received: download.received,
+                size,
+            });
+
+        self.request_next_chunk(behaviour, download);
+
+        notification
+    }
+
+    fn request_next_chunk(&mut self, behaviour: &mut Behaviour, download: Download) {
+        let len = (download.file.size - download.received).min(CHUNK_SIZE as u64) as u32;
+
+        let request_id = behaviour.send_request(
+            &download.peer,
+            ChunkRequest {
+                hash: download.file.hash.clone(),
+                offset: download.received,
+                len,
+            },
+        );
+
+        self.downloads.insert(request_id, download);
+    }
+
+    fn find_offer(&self, file_id: &str) -> Result<&(PeerId, File), TransferError> {
+        let mut matches = self
+            .offers
+            .iter()
+            .filter(|(hash, _)| hash.starts_with(file_id))
+            .map(|(_, offer)| offer);
+
+        match (matches.next(), matches.next()) {
+            (Some(offer), None) => Ok(offer),
+            (Some(_), Some(_)) => Err(TransferError::AmbiguousFileId(file_id.to_string())),
+            (None, _) => Err(TransferError::UnknownFile(file_id.to_string())),
+        }
+    }
+}
+
+async fn read_chunk(path: &Path, offset: u64, len: u32) -> std::io::Result<Vec<u8>> {
+    let mut file = tokio::fs::File::open(path).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+
+    let mut chunk = Vec::new();
+    file.take(len.min(CHUNK_SIZE) as u64)
+        .read_to_end(&mut chunk)
+        .await?;
+
+    Ok(chunk)
+}
+
+async fn finish(mut download: Download) -> Notification {
+    if let Err(e) = download.writer.flush().await {
+        return fail(download, e.to_string()).await;
+    }
+
+    let content = std::mem::take(&mut download.content);
+    if let Err(e) = download.file.verify(content) {
+        return fail(download, e.to_string()).await;
+    }
+
+    let target_path = match download.file.save_path(&download.dir).await {
+        Ok(path) => path,
+        Err(e) => return fail(download, e.to_string()).await,
+    };
+
+    // replaces the empty file that reserved the name
+    match tokio::fs::rename(&download.part_path, &target_path).await {
+        Ok(_) => {
+            log::info!("File: {} saved successfully", download.file.name);
+            Notification::DownloadFinished {
+                name: download.file.name,
+                path: target_path,
+                hash: download.file.hash,
+            }
+        }
+        Err(e) => {
+            if let Err(e) = tokio::fs::remove_file(&target_path).await {
+                log::warn!("Couldn't remove {}: {e}", target_path.display());
+            }
+            fail(download, e.to_string()).await
+        }
+    }
+}
+
+async fn fail(download: Download, reason: String) -> Notification {
+    log::error!("Download of {} failed: {reason}", download.file.name);
+
+    if let Err(e) = tokio::fs::remove_file(&download.part_path).await {
+        log::warn!("Couldn't remove {}: {e}", download.part_path.display());
+    }
+
+    Notification::DownloadFailed {
+        name: download.file.name,
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn read_chunk_respects_offset_and_len() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"Hello world").unwrap();
+
+        let chunk = read_chunk(file.path(), 6, 3).await.unwrap();
+        assert_eq!(chunk, b"wor");
+
+        let chunk = read_chunk(file.path(), 6, 100).await.unwrap();
+        assert_eq!(chunk, b"world");
+    }
+
+    #[test]
+    fn find_offer_by_prefix() {
+        let mut transfer = FileTransfer::default();
+        let peer = PeerId::random();
+
+        for prefix in ["abc123", "abd456"] {
+            let hash = format!("{prefix}{}", "0".repeat(58));
+            transfer.add_offer(
+                peer,
+                File {
+                    name: prefix.to_string(),
+                    size: 1,
+                    hash,
+                    path: None,
+                },
+            );
+        }
+
+        assert_eq!(transfer.find_offer("abc").unwrap().1.name, "abc123");
+        assert!(matches!(
+            transfer.find_offer("ab"),
+            Err(TransferError::AmbiguousFileId(_))
+        ));
+        assert!(matches!(
+            transfer.find_offer("ff"),
+            Err(TransferError::UnknownFile(_))
+        ));
+    }
+
+    #[test]
+    fn offer_with_invalid_hash_is_refused() {
+        let mut transfer = FileTransfer::default();
+        let file = File {
+            name: "a.txt".to_string(),
+            size: 1,
+            hash: "aaaaaaa€".to_string(),
+            path: None,
+        };
+
+        assert!(!transfer.add_offer(PeerId::random(), file));
+        assert!(matches!(
+            transfer.find_offer("aaaa"),
+            Err(TransferError::UnknownFile(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn cancel_all_removes_partial_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut transfer = FileTransfer::default();
+        let mut behaviour = behaviour();
+        let hash = "ab".repeat(32);
+
+        transfer.add_offer(
+            PeerId::random(),
+            File {
+                name: "report.pdf".to_string(),
+                size: 10,
+                hash: hash.clone(),
+                path: None,
+            },
+        );
+        transfer
+            .start_download(&mut behaviour, "abab", dir.path())
+            .await
+            .unwrap();
+
+        let part_path = dir.path().join(format!(".{hash}.part"));
+        assert!(part_path.exists());
+
+        let notifications = transfer.cancel_all("node is shutting down").await;
+
+        assert!(matches!(
+            notifications.as_slice(),
+            [Notification::DownloadFailed { name, reason }]
+                if name == "report.pdf" && reason == "node is shutting down"
+        ));
+        assert!(!part_path.exists());
+        assert!(transfer.downloads.is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::models::history::history_store::{HistoryRecord, HistoryStore};
+use libp2p::request_response::{self, ProtocolSupport};
+use libp2p::{PeerId, StreamProtocol};
+use serde::{Deserialize, Serialize};
+
+const PROTOCOL: StreamProtocol = StreamProtocol::new("/p2p-chat/history/1.0.0");
+/// Max number of messages asked from and served to a single peer
+pub const SYNC_LIMIT: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryRequest {
+    pub topic: String,
+    pub limit: usize,
+}
+
+/// Recent messages of the topic the peer holds, oldest first.
+/// Records aren't signed by their authors, so synced history is as trustworthy as the peer
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryResponse {
+    pub topic: String,
+    pub records: Vec<HistoryRecord>,
+}
+
+pub type Behaviour = request_response::cbor::Behaviour<HistoryRequest, HistoryResponse>;
+pub type Event = request_response::Event<HistoryRequest, HistoryResponse>;
+
+pub fn behaviour() -> Behaviour {
+    Behaviour::new(
+        [(PROTOCOL, ProtocolSupport::Full)],
+        request_response::Config::default(),
+    )
+}
+
+/// Asks every peer for recent messages of the topic
+pub fn request_history<'a>(
+    behaviour: &mut Behaviour,
+    peers: impl IntoIterator<Item = &'a PeerId>,
+    topic: &str,
+) {
+    for peer in peers {
+        log::info!("Requesting history of topic {topic} from {peer}");
+
+        behaviour.send_request(
+            peer,
+            HistoryRequest {
+                topic: topic.to_string(),
+                limit: SYNC_LIMIT,
+            },
+        );
+    }
+}
+
+/// Stores up to [`SYNC_LIMIT`] records of the response topic that aren't known yet,
+/// marked unverified and public, and returns them oldest first
+pub fn merge(history: &HistoryStore, response: HistoryResponse) -> Vec<HistoryRecord> {
+    let mut records: Vec<_> = response
+        .records
+        .into_iter()
+        .take(SYNC_LIMIT)
+        .filter(|record| record.topic == response.topic)
+        .map(|record| HistoryRecord {
+            verified: false,
+            private: false,
+            ..record
+        })
+        .filter(|record| match history.insert(record) {
+            Ok(inserted) => inserted,
+            Err(e) => {
+                log::error!("Couldn't save synced message: {e}");
+                false
+            }
+        })
+        .collect();
+
+    records.sort_by_key(|record| record.timestamp);
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::common::notification::Notification;
+    use crate::models::node::test_support::{first_listen_addr, start_local_node};
+    use std::time::Duration;
+
+    fn record(message_id: &str, topic: &str, timestamp: i64) -> HistoryRecord {
+        HistoryRecord {
+            message_id: message_id.to_string(),
+            topic: topic.to_string(),
+            sender: PeerId::random().to_string(),
+            timestamp,
+            text: Some(format!("message {message_id}")),
+            file_name: None,
+            verified: true,
+            private: false,
+        }
+    }
+
+    #[test]
+    fn merge_skips_known_and_foreign_records() {
+        let history = HistoryStore::in_memory().unwrap();
+        history.insert(&record("1", "ops", 1)).unwrap();
+
+        let response = HistoryResponse {
+            topic: "ops".to_string(),
+            records: vec![
+                record("3", "ops", 3),
+                record("1", "ops", 1),
+                record("2", "ops", 2),
+                record("4", "dev", 4),
+            ],
+        };
+
+        let ids: Vec<_> = merge(&history, response)
+            .into_iter()
+            .map(|record| record.message_id)
+            .collect();
+        assert_eq!(ids, ["2", "3"]);
+
+        let stored = history.last("ops", 10).unwrap();
+        assert!(stored[0].verified);
+        assert!(!stored[1].verified && !stored[2].verified);
+
+        assert_eq!(history.last("ops", 10).unwrap().len(), 3);
+        assert!(history.last("dev", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn merge_takes_at_most_sync_limit() {
+        let history = HistoryStore::in_memory().unwrap();
+        let response = HistoryResponse {
+            topic: "ops".to_string(),
+            records: (0..SYNC_LIMIT as i64 * 2)
+                .map(|n| record(&n.to_string(), "ops", n))
+                .collect(),
+        };
+
+        assert_eq!(merge(&history, response).len(), SYNC_LIMIT);
+        assert_eq!(
+            history.last("ops", SYNC_LIMIT * 2).unwrap().len(),
+            SYNC_LIMIT
+        );
+    }
+
+    #[tokio::test]
+    async fn history_is_synced_on_subscribe() {
+        let dir = tempfile::tempdir().unwrap();
+        let (server_dir, client_dir) = (dir.path().join("server"), dir.path().join("client"));
+
+        let stored = HistoryStore::open(&server_dir.join("data")).unwrap();
+        for (id, topic, timestamp) in [("1", "ops", 1), ("2", "ops", 2), ("3", "dev", 3)] {
+            stored.insert(&record(id, topic, timestamp)).unwrap();
+        }
+        drop(stored);
+
+        let mut server = start_local_node(&server_dir, Vec::new());
+        server.handle.subscribe("ops", None).await.unwrap();
+        server.handle.subscribe("dev", None).await.unwrap();
+
+        let addr = first_listen_addr(&server.handle).await;
+        tokio::spawn(async move { while server.events.recv().await.is_some() {} });
+
+        let mut client = start_local_node(&client_dir, vec![addr]);
+
+        let synced = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match client.events.recv().await.unwrap() {
+                    Notification::PeerJoined { .. } => {
+                        client.handle.subscribe("ops", None).await.unwrap();
+                    }
+                    Notification::SyncedHistory { topic, records } => break (topic, records),
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        let ids: Vec<_> = synced.1.iter().map(|r| r.message_id.as_str()).collect();
+        assert_eq!(synced.0, "ops");
+        assert_eq!(ids, ["1", "2"]);
+        assert_eq!(client.handle.history("ops", 10).await.unwrap(), synced.1);
+    }
+}
@@ -1,2 +1,10 @@
+mod addresses;
 mod behaviour;
+mod dialer;
+mod direct_message;
+mod file_transfer;
+mod history_sync;
+mod nickname;
+mod peer_directory;
 pub mod swarm_manager;
+mod topic_registry;
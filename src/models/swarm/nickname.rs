@@ -0,0 +1,39 @@
+use libp2p::request_response::{self, ProtocolSupport};
+use libp2p::{PeerId, StreamProtocol};
+use serde::{Deserialize, Serialize};
+
+const PROTOCOL: StreamProtocol = StreamProtocol::new("/p2p-chat/nick/1.0.0");
+
+/// Nickname of the sender, sent on connection and whenever it changes
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NickAnnouncement {
+    pub nick: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NickAck;
+
+pub type Behaviour = request_response::cbor::Behaviour<NickAnnouncement, NickAck>;
+pub type Event = request_response::Event<NickAnnouncement, NickAck>;
+
+pub fn behaviour() -> Behaviour {
+    Behaviour::new(
+        [(PROTOCOL, ProtocolSupport::Full)],
+        request_response::Config::default(),
+    )
+}
+
+pub fn announce<'a>(
+    behaviour: &mut Behaviour,
+    peers: impl IntoIterator<Item = &'a PeerId>,
+    nick: &str,
+) {
+    for peer in peers {
+        behaviour.send_request(
+            peer,
+            NickAnnouncement {
+                nick: nick.to_string(),
+            },
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use crate::models::common::errors::DirectMessageError;
+use crate::models::common::peer_name::PeerName;
+use crate::models::common::response::Direction;
+use crate::models::common::short_peer_id::{DEFAULT_SHORT_ID_LEN, ShortPeerId};
+use libp2p::gossipsub::TopicHash;
+use libp2p::{Multiaddr, PeerId};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Connected and discovered peers and nicknames they announced. Nicknames aren't unique,
+/// so peers are always shown with a short id that is unique among known peers
+#[derive(Debug)]
+pub struct PeerDirectory {
+    presence: HashMap<PeerId, Presence>,
+    nicks: HashMap<PeerId, String>,
+    short_id_len: usize,
+}
+
+/// What is known about a peer that is online
+#[derive(Debug, Default)]
+pub struct Presence {
+    /// direction of the first connection, None if the peer is only discovered
+    pub connection: Option<Direction>,
+    pub discovered: bool,
+    pub addresses: Vec<Multiaddr>,
+    pub rtt: Option<Duration>,
+    /// topics the peer is subscribed to, as seen on the wire
+    pub topics: HashSet<TopicHash>,
+}
+
+impl Default for PeerDirectory {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHORT_ID_LEN)
+    }
+}
+
+impl PeerDirectory {
+    /// `short_id_len` is the minimal length of shown ids
+    pub fn new(short_id_len: usize) -> Self {
+        Self {
+            presence: HashMap::new(),
+            nicks: HashMap::new(),
+            short_id_len,
+        }
+    }
+
+    /// Called on the first connection to the peer
+    pub fn on_connected(&mut self, peer_id: PeerId, direction: Direction, addr: Multiaddr) {
+        let presence = self.presence.entry(peer_id).or_default();
+
+        presence.connection = Some(direction);
+        add_address(presence, addr);
+    }
+
+    /// Called when the last connection to the peer is closed.
+    /// Nickname of the peer is kept to show its old messages
+    pub fn on_disconnected(&mut self, peer_id: &PeerId) {
+        let Some(presence) = self.presence.get_mut(peer_id) else {
+            return;
+        };
+
+        presence.connection = None;
+        presence.rtt = None;
+        presence.topics.clear();
+
+        if !presence.discovered {
+            self.presence.remove(peer_id);
+        }
+    }
+
+    pub fn on_discovered(&mut self, peer_id: PeerId, addr: Multiaddr) {
+        let presence = self.presence.entry(peer_id).or_default();
+
+        presence.discovered = true;
+        add_address(presence, addr);
+    }
+
+    pub fn on_expired(&mut self, peer_id: &PeerId) {
+        let Some(presence) = self.presence.get_mut(peer_id) else {
+            return;
+        };
+
+        presence.discovered = false;
+
+        if presence.connection.is_none() {
+            self.presence.remove(peer_id);
+        }
+    }
+
+    pub fn set_rtt(&mut self, peer_id: &PeerId, rtt: Duration) {
+        if let Some(presence) = self.presence.get_mut(peer_id) {
+            presence.rtt = Some(rtt);
+        }
+    }
+
+    pub fn on_subscribed(&mut self, peer_id: &PeerId, topic: TopicHash) {
+        if let Some(presence) = self.presence.get_mut(peer_id) {
+            presence.topics.insert(topic);
+        }
+    }
+
+    pub fn on_unsubscribed(&mut self, peer_id: &PeerId, topic: &TopicHash) {
+        if let Some(presence) = self.presence.get_mut(peer_id) {
+            presence.topics.remove(topic);
+        }
+    }
+
+    /// Peers that are connected or discovered
+    pub fn online(&self) -> impl Iterator<Item = (&PeerId, &Presence)> {
+        self.presence.iter()
+    }
+
+    /// Returns false if the peer already had this nickname
+    pub fn set(&mut self, peer_id: PeerId, nick: String) -> bool {
+        self.nicks.insert(peer_id, nick.clone()).as_ref() != Some(&nick)
+    }
+
+    pub fn nick(&self, peer_id: &PeerId) -> Option<&str> {
+        self.nicks.get(peer_id).map(String::as_str)
+    }
+
+    pub fn short_id(&self, peer_id: &PeerId) -> ShortPeerId {
+        ShortPeerId::unique(peer_id, self.known(), self.short_id_len)
+    }
+
+    pub fn name(&self, peer_id: &PeerId) -> PeerName {
+        PeerName::new(self.short_id(peer_id), self.nick(peer_id))
+    }
+
+    /// Other peers using the same nickname as `peer_id`
+    pub fn conflicts(&self, peer_id: &PeerId) -> Vec<PeerId> {
+        let Some(nick) = self.nick(peer_id) else {
+            return Vec::new();
+        };
+
+        self.nicks
+            .iter()
+            .filter(|(other, other_nick)| *other != peer_id && other_nick.as_str() == nick)
+            .map(|(other, _)| *other)
+            .collect()
+    }
+
+    /// Finds the peer by full PeerId, short id as it is shown or nickname.
+    /// Ids go first, so a peer can't take over another one's id with its nickname
+    pub fn resolve(&self, query: &str) -> Result<PeerId, DirectMessageError> {
+        let query = query.trim();
+
+        if let Ok(peer_id) = query.parse::<PeerId>() {
+            return Ok(peer_id);
+        }
+
+        if let Ok(short_id) = query.parse::<ShortPeerId>() {
+            let by_short_id: Vec<PeerId> = self
+                .known()
+                .filter(|peer_id| short_id.matches(peer_id))
+                .copied()
+                .collect();
+
+            if !by_short_id.is_empty() {
+                return single(by_short_id, query);
+            }
+        }
+
+        let by_nick: Vec<PeerId> = self
+            .nicks
+            .iter()
+            .filter(|(_, nick)| nick.as_str() == query)
+            .map(|(peer_id, _)| *peer_id)
+            .collect();
+
+        single(by_nick, query)
+    }
+
+    fn known(&self) -> impl Iterator<Item = &PeerId> {
+        self.presence.keys().chain(
+            self.nicks
+                .keys()
+                .filter(|peer_id| !self.presence.contains_key(*peer_id)),
+        )
+    }
+}
+
+fn add_address(presence: &mut Presence, addr: Multiaddr) {
+    if !presence.addresses.contains(&addr) {
+        presence.addresses.push(addr);
+    }
+}
+
+fn single(peers: Vec<PeerId>, query: &str) -> Result<PeerId, DirectMessageError> {
+    match peers.as_slice() {
+        [peer_id] => Ok(*peer_id),
+        [] => Err(DirectMessageError::UnknownPeer(query.to_string())),
+        _ => Err(DirectMessageError::AmbiguousPeer(query.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr() -> Multiaddr {
+        "/ip4/10.0.0.1/tcp/4001".parse().unwrap()
+    }
+
+    #[test]
+    fn set_reports_changes() {
+        let mut directory = PeerDirectory::default();
+        let peer_id = PeerId::random();
+
+        assert!(directory.set(peer_id, "alice".to_string()));
+        assert!(!directory.set(peer_id, "alice".to_string()));
+        assert!(directory.set(peer_id, "bob".to_string()));
+
+        assert_eq!(directory.nick(&peer_id), Some("bob"));
+    }
+
+    #[test]
+    fn same_nick_is_a_conflict() {
+        let mut directory = PeerDirectory::default();
+        let alice = PeerId::random();
+        let impostor = PeerId::random();
+
+        directory.set(alice, "alice".to_string());
+        assert_eq!(directory.resolve("alice").unwrap(), alice);
+        assert!(directory.conflicts(&alice).is_empty());
+
+        directory.set(impostor, "alice".to_string());
+        assert_eq!(directory.conflicts(&alice), vec![impostor]);
+        assert!(matches!(
+            directory.resolve("alice"),
+            Err(DirectMessageError::AmbiguousPeer(_))
+        ));
+        assert_ne!(directory.name(&alice), directory.name(&impostor));
+    }
+
+    #[test]
+    fn resolve_full_and_short_id() {
+        let mut directory = PeerDirectory::default();
+        let peers = [PeerId::random(), PeerId::random()];
+        for peer_id in peers {
+            directory.on_connected(peer_id, Direction::Outbound, addr());
+        }
+
+        let unknown = PeerId::random();
+        assert_eq!(directory.resolve(&unknown.to_string()).unwrap(), unknown);
+
+        let shown = directory.short_id(&peers[0]).to_string();
+        assert_eq!(directory.resolve(&shown).unwrap(), peers[0]);
+
+        let res = directory.resolve("…x0");
+        assert!(matches!(res, Err(DirectMessageError::UnknownPeer(_))));
+
+        let res = directory.resolve("");
+        assert!(matches!(res, Err(DirectMessageError::UnknownPeer(_))));
+    }
+
+    #[test]
+    fn nick_cannot_shadow_short_id() {
+        let mut directory = PeerDirectory::default();
+        let victim = PeerId::random();
+        let attacker = PeerId::random();
+        directory.on_connected(victim, Direction::Outbound, addr());
+        directory.on_connected(attacker, Direction::Inbound, addr());
+
+        let shown = directory.short_id(&victim).to_string();
+        let tail = shown.trim_start_matches('…').to_string();
+        directory.set(attacker, tail.clone());
+        directory.set(victim, "dave's laptop".to_string());
+
+        assert_eq!(directory.resolve(&shown).unwrap(), victim);
+        assert_eq!(directory.resolve(&tail).unwrap(), victim);
+        assert_eq!(directory.resolve("dave's laptop").unwrap(), victim);
+    }
+
+    #[test]
+    fn short_ids_stay_unique() {
+        let mut directory = PeerDirectory::new(1);
+        let peers: Vec<PeerId> = (0..100).map(|_| PeerId::random()).collect();
+        for peer_id in &peers {
+            directory.on_discovered(*peer_id, addr());
+        }
+
+        let shown: HashSet<String> = peers
+            .iter()
+            .map(|peer_id| directory.short_id(peer_id).to_string())
+            .collect();
+        assert_eq!(shown.len(), peers.len());
+
+        for peer_id in &peers {
+            let shown = directory.short_id(peer_id).to_string();
+            assert_eq!(directory.resolve(&shown).unwrap(), *peer_id);
+        }
+    }
+
+    #[test]
+    fn presence_follows_connection() {
+        let mut directory = PeerDirectory::default();
+        let peer_id = PeerId::random();
+        let topic = TopicHash::from_raw("ops");
+
+        directory.on_discovered(peer_id, addr());
+        directory.on_connected(peer_id, Direction::Inbound, addr());
+        directory.on_subscribed(&peer_id, topic.clone());
+        directory.set_rtt(&peer_id, Duration::from_millis(3));
+
+        let (_, presence) = directory.online().next().unwrap();
+        assert_eq!(presence.connection, Some(Direction::Inbound));
+        assert_eq!(presence.addresses, vec![addr()]);
+        assert!(presence.topics.contains(&topic));
+
+        directory.on_disconnected(&peer_id);
+        let (_, presence) = directory.online().next().unwrap();
+        assert!(presence.connection.is_none());
+        assert!(presence.topics.is_empty());
+        assert!(presence.rtt.is_none());
+
+        directory.on_expired(&peer_id);
+        assert!(directory.online().next().is_none());
+    }
+}
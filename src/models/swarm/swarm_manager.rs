@@ -1,64 +1,171 @@
-use crate::models::common::command::Command;
-use crate::models::common::errors::SendingError;
-use crate::models::common::message::Message;
-use crate::models::common::short_peer_id::ShortPeerId;
+use crate::models::common::command::{Command, respond};
+use crate::models::common::errors::{SendingError, TopicError};
+use crate::models::common::message::{Message, ReceivedMessage};
+use crate::models::common::notification::Notification;
+use crate::models::common::peer_name::validate_nick;
+use crate::models::common::response::{
+    ConnectOutcome, Direction, NickOutcome, PeerInfo, SendOutcome, SubscribeOutcome, SwitchOutcome,
+    UnsubscribeOutcome,
+};
+use crate::models::common::topic_key::TopicKey;
+use crate::models::history::history_store::{HistoryRecord, HistoryStore};
+use crate::models::swarm::addresses::Addresses;
 use crate::models::swarm::behaviour::{ChatBehaviour, ChatBehaviourEvent};
+use crate::models::swarm::dialer::Dialer;
+use crate::models::swarm::direct_message::DirectMessages;
+use crate::models::swarm::file_transfer::FileTransfer;
+use crate::models::swarm::history_sync::{self, HistoryResponse, SYNC_LIMIT};
+use crate::models::swarm::nickname::{self, NickAck};
+use crate::models::swarm::peer_directory::PeerDirectory;
+use crate::models::swarm::topic_registry::TopicRegistry;
 use crate::traits::decode::Decode;
 use crate::traits::encode::Encode;
+use jiff::Timestamp;
 use libp2p::futures::StreamExt;
-use libp2p::gossipsub::IdentTopic;
+use libp2p::identity::Keypair;
 use libp2p::mdns::Event;
 use libp2p::swarm::SwarmEvent;
-use libp2p::{Swarm, gossipsub, noise, tcp, yamux};
+use libp2p::{Multiaddr, PeerId, Swarm, gossipsub, noise, ping, request_response, tcp, yamux};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
+/// Max number of messages returned by search
+const SEARCH_LIMIT: usize = 50;
+/// How often due redials of explicit peers are checked
+const DIAL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long peers are given to learn that the node leaves its topics and closes connections
+const SHUTDOWN_GRACE: Duration = Duration::from_millis(500);
+/// Used when no listen addresses are configured
+const DEFAULT_LISTEN_ADDRS: &[&str] = &[
+    #[cfg(feature = "quic")]
+    "/ip4/0.0.0.0/udp/0/quic-v1",
+    "/ip4/0.0.0.0/tcp/0",
+];
+
+/// Discovery and gossipsub settings of the swarm
+#[derive(Debug, Clone)]
+pub struct SwarmConfig {
+    /// find peers in the local network with mDNS
+    pub mdns: bool,
+    pub heartbeat_interval: Duration,
+}
+
 pub struct SwarmManager {
     swarm: Swarm<ChatBehaviour>,
     command_receiver: mpsc::Receiver<Command>,
-    msg_sender: mpsc::Sender<(Message, ShortPeerId)>,
-    current_topic: Option<IdentTopic>,
+    notification_sender: mpsc::Sender<Notification>,
+    topics: TopicRegistry,
+    file_transfer: FileTransfer,
+    direct_messages: DirectMessages,
+    history: HistoryStore,
+    dialer: Dialer,
+    addresses: Addresses,
+    directory: PeerDirectory,
+    /// announced to every connected peer
+    nick: Option<String>,
+}
+
+impl Default for SwarmConfig {
+    fn default() -> Self {
+        Self {
+            mdns: true,
+            heartbeat_interval: Duration::from_secs(10),
+        }
+    }
 }
 
 impl SwarmManager {
+    /// Listens on `listen_addrs` or on any interface with a random port if the list is empty
     pub fn build(
-        msg_sender: mpsc::Sender<(Message, ShortPeerId)>,
+        keypair: Keypair,
+        history: HistoryStore,
+        listen_addrs: Vec<Multiaddr>,
+        config: SwarmConfig,
+        notification_sender: mpsc::Sender<Notification>,
         command_receiver: mpsc::Receiver<Command>,
     ) -> anyhow::Result<Self> {
         log::info!("Creating SwarmManager");
-        let mut swarm = build_swarm()?;
-
-        let addresses = vec![
-            #[cfg(feature = "quic")]
-            "/ip4/0.0.0.0/udp/0/quic-v1",
-            "/ip4/0.0.0.0/tcp/0",
-        ];
+        let mut swarm = build_swarm(keypair, &config)?;
+
+        let listen_addrs = match listen_addrs.is_empty() {
+            true => DEFAULT_LISTEN_ADDRS
+                .iter()
+                .map(|addr| addr.parse())
+                .collect::<Result<_, _>>()?,
+            false => listen_addrs,
+        };
 
-        for addr in addresses {
-            swarm.listen_on(addr.parse()?)?;
+        for addr in listen_addrs {
+            swarm.listen_on(addr)?;
         }
 
         Ok(Self {
             swarm,
             command_receiver,
-            msg_sender,
-            current_topic: None,
+            notification_sender,
+            topics: TopicRegistry::default(),
+            file_transfer: FileTransfer::default(),
+            direct_messages: DirectMessages::default(),
+            history,
+            dialer: Dialer::default(),
+            addresses: Addresses::default(),
+            directory: PeerDirectory::default(),
+            nick: None,
         })
     }
 
     pub async fn run(&mut self) {
         log::info!("Running...");
 
+        let mut dial_interval = tokio::time::interval(DIAL_INTERVAL);
+
         loop {
             tokio::select! {
                 event = self.swarm.select_next_some() => self.handle_event(event).await,
-                command = self.command_receiver.recv() => self.handle_command(command).await,
+                command = self.command_receiver.recv() => {
+                    let stop = matches!(command, Some(Command::Shutdown { .. }) | None);
+
+                    self.handle_command(command).await;
+
+                    if stop {
+                        break;
+                    }
+                }
+                _ = dial_interval.tick() => self.dial_due(),
             }
         }
+
+        log::info!("Stopped");
+    }
+
+    /// Peers dialed on start and kept connected, in addition to the ones found by mDNS
+    pub fn with_peers(mut self, addrs: impl IntoIterator<Item = Multiaddr>) -> Self {
+        for addr in addrs {
+            self.dialer.add(addr);
+        }
+        self
+    }
+
+    /// Minimal length of peer ids shown to the user
+    pub fn with_short_id_len(mut self, len: usize) -> Self {
+        self.directory = PeerDirectory::new(len);
+        self
+    }
+
+    pub fn with_nick(mut self, nick: Option<String>) -> Self {
+        self.nick = nick;
+        self
+    }
+
+    fn dial_due(&mut self) {
+        let swarm = &mut self.swarm;
+        self.dialer
+            .dial_due(Instant::now(), |opts| swarm.dial(opts));
     }
 
     #[cfg(test)]
     pub fn with_topic(mut self, topic_name: impl Into<String>) -> Self {
-        match self.subscribe(topic_name) {
+        match self.subscribe(topic_name, None) {
             Ok(res) => match res {
                 true => log::info!("Swarm subscribed to the topic successfully"),
                 false => log::warn!("Swarm is already subscribed to to this topic"),
@@ -72,8 +179,9 @@ impl SwarmManager {
         match event {
             SwarmEvent::Behaviour(ChatBehaviourEvent::Mdns(mdns_event)) => match mdns_event {
                 Event::Discovered(peers) => {
-                    for (peer_id, _multiaddr) in peers {
+                    for (peer_id, multiaddr) in peers {
                         log::info!("mDNS discovered a new peer: {peer_id}");
+                        self.directory.on_discovered(peer_id, multiaddr);
                         self.swarm
                             .behaviour_mut()
                             .gossipsub
@@ -83,6 +191,12 @@ impl SwarmManager {
                 Event::Expired(peers) => {
                     for (peer_id, _multiaddr) in peers {
                         log::info!("mDNS discover peer has expired: {peer_id}");
+                        self.directory.on_expired(&peer_id);
+
+                        if self.dialer.is_target(&peer_id) {
+                            continue;
+                        }
+
                         self.swarm
                             .behaviour_mut()
                             .gossipsub
@@ -95,7 +209,32 @@ impl SwarmManager {
                 message_id: id,
                 message,
             })) => {
-                let msg = match Message::decode(&mut message.data.as_slice()) {
+                let Some(topic) = self.topics.name_of(&message.topic) else {
+                    log::warn!("Got message {id} for unknown topic: {}", message.topic);
+                    return;
+                };
+
+                let source = message.source.unwrap_or(peer_id);
+
+                let data = match self.topics.key(topic) {
+                    Some(key) => match key.open(&message.data) {
+                        Ok(data) => data,
+                        Err(e) => {
+                            log::warn!("Message {id} in topic {topic} from {source}: {e}");
+                            let notification = Notification::UndecryptableMessage {
+                                topic: topic.to_string(),
+                                sender: self.directory.name(&source),
+                                sender_id: source,
+                                timestamp: Timestamp::now().as_second(),
+                            };
+                            notify(&self.notification_sender, notification).await;
+                            return;
+                        }
+                    },
+                    None => message.data,
+                };
+
+                let mut msg = match Message::decode(&mut data.as_slice()) {
                     Ok(msg) => msg,
                     Err(e) => {
                         log::error!("Couldn't decode message: {e}");
@@ -103,20 +242,162 @@ impl SwarmManager {
                     }
                 };
 
+                // a refused offer is neither saved nor shown
+                if let Some(file) = &msg.file
+                    && !self.file_transfer.add_offer(source, file.clone())
+                {
+                    msg.file = None;
+                }
+
                 if msg.is_empty() {
                     return;
                 }
 
-                let sender_peer_id = ShortPeerId::from(&peer_id);
+                let record = HistoryRecord::new(
+                    id.to_string(),
+                    topic.to_string(),
+                    source.to_string(),
+                    &msg,
+                    self.topics.is_private(topic),
+                );
+                if let Err(e) = self.history.insert(&record) {
+                    log::error!("Couldn't save message {id} to history: {e}");
+                }
+
+                let received = ReceivedMessage {
+                    id: record.message_id,
+                    topic: topic.to_string(),
+                    sender: self.directory.name(&source),
+                    sender_id: source,
+                    timestamp: record.timestamp,
+                    msg,
+                };
 
-                log::info!("Got message: '{msg}' with id: {id} from peer: {peer_id}");
-                match self.msg_sender.send((msg, sender_peer_id)).await {
-                    Ok(_) => {}
-                    Err(e) => log::error!("Couldn't send message to client: {e}"),
+                log::info!(
+                    "Got message: '{}' with id: {id} from peer: {peer_id} in topic: {topic}",
+                    received.msg
+                );
+                notify(&self.notification_sender, Notification::Message(received)).await;
+            }
+            SwarmEvent::Behaviour(ChatBehaviourEvent::Gossipsub(
+                gossipsub::Event::Subscribed { peer_id, topic },
+            )) => {
+                self.directory.on_subscribed(&peer_id, topic);
+            }
+            SwarmEvent::Behaviour(ChatBehaviourEvent::Gossipsub(
+                gossipsub::Event::Unsubscribed { peer_id, topic },
+            )) => {
+                self.directory.on_unsubscribed(&peer_id, &topic);
+            }
+            SwarmEvent::Behaviour(ChatBehaviourEvent::Ping(ping::Event {
+                peer,
+                result: Ok(rtt),
+                ..
+            })) => {
+                self.directory.set_rtt(&peer, rtt);
+            }
+            SwarmEvent::Behaviour(ChatBehaviourEvent::DirectMessage(event)) => {
+                let notification = self.direct_messages.handle_event(
+                    &mut self.swarm.behaviour_mut().direct_message,
+                    &self.directory,
+                    event,
+                );
+
+                if let Some(notification) = notification {
+                    notify(&self.notification_sender, notification).await;
+                }
+            }
+            SwarmEvent::Behaviour(ChatBehaviourEvent::Nickname(event)) => {
+                self.handle_nickname_event(event).await;
+            }
+            SwarmEvent::Behaviour(ChatBehaviourEvent::HistorySync(event)) => {
+                self.handle_history_sync_event(event).await;
+            }
+            SwarmEvent::Behaviour(ChatBehaviourEvent::FileTransfer(event)) => {
+                let notification = self
+                    .file_transfer
+                    .handle_event(
+                        &mut self.swarm.behaviour_mut().file_transfer,
+                        &self.directory,
+                        event,
+                    )
+                    .await;
+
+                if let Some(notification) = notification {
+                    notify(&self.notification_sender, notification).await;
                 }
             }
             SwarmEvent::NewListenAddr { address, .. } => {
                 log::info!("Local node is listening on {address}");
+                self.addresses.add_listen(address);
+            }
+            SwarmEvent::ExpiredListenAddr { address, .. } => {
+                log::info!("Local node stopped listening on {address}");
+                self.addresses.remove_listen(&address);
+            }
+            SwarmEvent::NewExternalAddrCandidate { address } => {
+                log::info!("Peer observed local node at {address}");
+                self.addresses.add_observed(address);
+            }
+            SwarmEvent::ConnectionEstablished {
+                peer_id,
+                connection_id,
+                endpoint,
+                num_established,
+                ..
+            } => {
+                if self.dialer.on_connected(connection_id, peer_id, &endpoint) {
+                    log::info!("Connected to explicit peer {peer_id}");
+                    self.swarm
+                        .behaviour_mut()
+                        .gossipsub
+                        .add_explicit_peer(&peer_id);
+                }
+
+                if num_established.get() != 1 {
+                    return;
+                }
+
+                let direction = match endpoint.is_dialer() {
+                    true => Direction::Outbound,
+                    false => Direction::Inbound,
+                };
+                self.directory.on_connected(
+                    peer_id,
+                    direction,
+                    endpoint.get_remote_address().clone(),
+                );
+
+                if let Some(nick) = &self.nick {
+                    nickname::announce(&mut self.swarm.behaviour_mut().nickname, [&peer_id], nick);
+                }
+
+                let notification = Notification::PeerJoined {
+                    name: self.directory.name(&peer_id),
+                    peer_id,
+                };
+                notify(&self.notification_sender, notification).await;
+            }
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                num_established: 0,
+                ..
+            } => {
+                self.dialer.on_disconnected(peer_id);
+
+                let name = self.directory.name(&peer_id);
+                self.directory.on_disconnected(&peer_id);
+
+                let notification = Notification::PeerLeft { name, peer_id };
+                notify(&self.notification_sender, notification).await;
+            }
+            SwarmEvent::OutgoingConnectionError {
+                connection_id,
+                error,
+                ..
+            } => {
+                log::warn!("Outgoing connection failed: {error}");
+                self.dialer.on_dial_failed(connection_id);
             }
             _ => {}
         }
@@ -126,111 +407,544 @@ impl SwarmManager {
         match command {
             Some(Command::SendMessage {
                 response_sender,
+                topic_name,
                 msg,
             }) => {
                 log::info!("Sending message...");
 
-                let ans = match self.send_message(msg) {
-                    Ok(_) => "Ok".to_string(),
-                    Err(SendingError::NoSubscribedTopic) => {
-                        log::warn!("Swarm not subscribed to any topic");
-                        "You are not subscribed to any topic".to_string()
-                    }
-                    Err(SendingError::CantEncodeMessage(e)) => {
-                        log::error!("Can't encode message: {e}");
-                        "Something wrong with the message".to_string()
-                    }
-                    Err(SendingError::Other(e)) => {
-                        log::error!("Sending error: {e}");
-                        "Something went wrong while sending message".to_string()
-                    }
-                };
+                let ans = self.send_message(topic_name.as_deref(), msg);
 
-                if let Err(e) = response_sender.send(ans) {
-                    log::error!("Response wasn't sent to client: {e}");
+                if let Err(e) = &ans {
+                    log::warn!("Couldn't send message: {e}");
                 }
+
+                respond(response_sender, ans);
             }
             Some(Command::Subscribe {
                 response_sender,
                 topic_name,
+                key,
             }) => {
                 log::info!("Subscribing topic {topic_name}...");
 
-                let ans = match self.subscribe(topic_name) {
-                    Ok(res) => match res {
-                        true => "You have subscribed to the topic successfully",
-                        false => "You are already subscribed to to this topic",
-                    },
-                    Err(_) => "Something went wrong",
-                };
+                let private = key.is_some();
 
-                if let Err(e) = response_sender.send(ans.to_string()) {
-                    log::error!("Response wasn't sent to client: {e}");
+                let ans = match self.subscribe(topic_name.clone(), key) {
+                    // history of private topics isn't synced, peers would get it unencrypted
+                    Ok(true) if private => Ok(SubscribeOutcome::SubscribedPrivate),
+                    Ok(true) => {
+                        let peers: Vec<PeerId> = self.swarm.connected_peers().copied().collect();
+                        history_sync::request_history(
+                            &mut self.swarm.behaviour_mut().history_sync,
+                            &peers,
+                            &topic_name,
+                        );
+
+                        Ok(SubscribeOutcome::Subscribed)
+                    }
+                    Ok(false) => Ok(SubscribeOutcome::AlreadySubscribed),
+                    Err(e) => {
+                        log::error!("Couldn't subscribe topic {topic_name}: {e}");
+                        Err(e)
+                    }
                 };
+
+                respond(response_sender, ans);
             }
             Some(Command::Unsubscribe {
                 response_sender,
                 topic_name,
             }) => {
-                log::info!("Subscribing topic {topic_name}...");
+                log::info!("Unsubscribing topic {topic_name}...");
+
+                let ans = match self.unsubscribe(topic_name.clone()) {
+                    true => Ok(UnsubscribeOutcome {
+                        active: self.topics.active_name().map(str::to_string),
+                    }),
+                    false => Err(TopicError::NotSubscribed(topic_name)),
+                };
+
+                respond(response_sender, ans);
+            }
+            Some(Command::DirectMessage {
+                response_sender,
+                peer,
+                msg,
+            }) => {
+                log::info!("Sending direct message to {peer}...");
+
+                match self.directory.resolve(&peer) {
+                    Ok(peer_id) => self.direct_messages.send(
+                        &mut self.swarm.behaviour_mut().direct_message,
+                        peer_id,
+                        msg,
+                        response_sender,
+                    ),
+                    Err(e) => {
+                        log::warn!("Couldn't resolve peer {peer}: {e}");
+                        respond(response_sender, Err(e));
+                    }
+                }
+            }
+            Some(Command::History {
+                response_sender,
+                topic_name,
+                limit,
+            }) => {
+                log::info!("Loading history of topic {topic_name}...");
+
+                let ans = self.history.last(&topic_name, limit);
 
-                let ans = match self.unsubscribe(topic_name) {
-                    true => "You have unsubscribed from the topic successfully",
-                    false => "You haven't been subscribed to this topic",
+                if let Err(e) = &ans {
+                    log::error!("Couldn't read history: {e}");
+                }
+
+                respond(response_sender, ans);
+            }
+            Some(Command::Search {
+                response_sender,
+                text,
+            }) => {
+                log::info!("Searching history for '{text}'...");
+
+                let ans = self.history.search(&text, SEARCH_LIMIT);
+
+                if let Err(e) = &ans {
+                    log::error!("Couldn't read history: {e}");
+                }
+
+                respond(response_sender, ans);
+            }
+            Some(Command::Download {
+                response_sender,
+                file_id,
+                dir,
+            }) => {
+                log::info!("Downloading file {file_id}...");
+
+                let ans = self
+                    .file_transfer
+                    .start_download(
+                        &mut self.swarm.behaviour_mut().file_transfer,
+                        &file_id,
+                        &dir,
+                    )
+                    .await;
+
+                if let Err(e) = &ans {
+                    log::warn!("Couldn't start download: {e}");
+                }
+
+                respond(response_sender, ans);
+            }
+            Some(Command::Connect {
+                response_sender,
+                addr,
+            }) => {
+                log::info!("Connecting to {addr}...");
+
+                let ans = match self.dialer.add(addr.clone()) {
+                    true => {
+                        self.dial_due();
+                        ConnectOutcome::Dialing(addr)
+                    }
+                    false => ConnectOutcome::AlreadyKnown(addr),
                 };
 
-                match response_sender.send(ans.to_string()) {
-                    Ok(_) => {}
-                    Err(e) => log::error!("Response wasn't sent to client: {e}"),
+                respond(response_sender, ans);
+            }
+            Some(Command::Peers { response_sender }) => {
+                respond(response_sender, peer_infos(&self.directory, &self.topics));
+            }
+            Some(Command::TopicPeers {
+                response_sender,
+                topic_name,
+            }) => {
+                let ans = match self.topics.get(&topic_name) {
+                    Some(topic) => Ok(self.topic_peer_count(&topic.hash())),
+                    None => Err(TopicError::NotSubscribed(topic_name)),
                 };
+
+                respond(response_sender, ans);
+            }
+            Some(Command::Addrs { response_sender }) => {
+                respond(response_sender, self.addresses.list());
+            }
+            Some(Command::Nick {
+                response_sender,
+                nick,
+            }) => {
+                let ans = match nick {
+                    None => Ok(NickOutcome::Current(self.nick.clone())),
+                    Some(nick) => validate_nick(&nick).map(|nick| {
+                        log::info!("Announcing nickname {nick}...");
+
+                        let peers: Vec<PeerId> = self.swarm.connected_peers().copied().collect();
+                        nickname::announce(&mut self.swarm.behaviour_mut().nickname, &peers, &nick);
+
+                        self.nick = Some(nick.clone());
+                        NickOutcome::Changed(nick)
+                    }),
+                };
+
+                respond(response_sender, ans);
+            }
+            Some(Command::Switch {
+                response_sender,
+                topic_name,
+            }) => {
+                log::info!("Switching to topic {topic_name}...");
+
+                let ans = match self.topics.switch(&topic_name) {
+                    true => Ok(SwitchOutcome { active: topic_name }),
+                    false => Err(TopicError::NotSubscribed(topic_name)),
+                };
+
+                respond(response_sender, ans);
+            }
+            Some(Command::Shutdown { response_sender }) => {
+                self.shutdown().await;
+                respond(response_sender, ());
+            }
+            None => {
+                log::warn!("Client is gone");
+                self.shutdown().await;
             }
-            None => {}
         }
     }
 
-    pub fn subscribe(&mut self, topic_name: impl Into<String>) -> anyhow::Result<bool> {
-        let topic = IdentTopic::new(topic_name);
+    /// Leaves all topics, cancels downloads and removes their partial files,
+    /// closes connections and flushes history
+    async fn shutdown(&mut self) {
+        log::info!("Shutting down...");
+
+        for topic_name in self.topics.names() {
+            self.unsubscribe(topic_name);
+        }
+
+        for notification in self.file_transfer.cancel_all("node is shutting down").await {
+            notify(&self.notification_sender, notification).await;
+        }
 
-        let res = self.swarm.behaviour_mut().gossipsub.subscribe(&topic)?;
+        // gossipsub sends the unsubscriptions while the connections are still open
+        self.poll_while_connected(SHUTDOWN_GRACE).await;
 
-        self.current_topic = Some(topic);
+        let peers: Vec<PeerId> = self.swarm.connected_peers().copied().collect();
+        for peer in peers {
+            let _ = self.swarm.disconnect_peer_id(peer);
+        }
 
-        Ok(res)
+        self.poll_while_connected(SHUTDOWN_GRACE).await;
+
+        if let Err(e) = self.history.flush() {
+            log::error!("Couldn't flush history: {e}");
+        }
     }
 
-    pub fn unsubscribe(&mut self, topic_name: String) -> bool {
-        let topic = IdentTopic::new(topic_name);
+    /// Handles swarm events for `duration` or until there are no connected peers
+    async fn poll_while_connected(&mut self, duration: Duration) {
+        let deadline = tokio::time::sleep(duration);
+        tokio::pin!(deadline);
+
+        while self.swarm.connected_peers().next().is_some() {
+            tokio::select! {
+                _ = &mut deadline => return,
+                event = self.swarm.select_next_some() => self.handle_event(event).await,
+            }
+        }
+    }
+
+    async fn handle_nickname_event(&mut self, event: nickname::Event) {
+        match event {
+            nickname::Event::Message {
+                peer,
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+                ..
+            } => {
+                if self
+                    .swarm
+                    .behaviour_mut()
+                    .nickname
+                    .send_response(channel, NickAck)
+                    .is_err()
+                {
+                    log::warn!("Couldn't acknowledge nickname of {peer}");
+                }
+
+                let nick = match validate_nick(&request.nick) {
+                    Ok(nick) => nick,
+                    Err(e) => {
+                        log::warn!("Peer {peer} announced invalid nickname: {e}");
+                        return;
+                    }
+                };
 
-        let res = self.swarm.behaviour_mut().gossipsub.unsubscribe(&topic);
+                let old = self.directory.name(&peer);
 
-        self.current_topic = None;
+                if !self.directory.set(peer, nick) {
+                    return;
+                }
 
-        res
+                log::info!("Peer {peer} is now known as {}", request.nick);
+
+                let notification = Notification::NickChanged {
+                    peer_id: peer,
+                    old,
+                    new: self.directory.name(&peer),
+                    conflicts: self
+                        .directory
+                        .conflicts(&peer)
+                        .into_iter()
+                        .map(|other| (self.directory.name(&other), other))
+                        .collect(),
+                };
+                notify(&self.notification_sender, notification).await;
+            }
+            nickname::Event::OutboundFailure { peer, error, .. } => {
+                log::warn!("Couldn't announce nickname to {peer}: {error}");
+            }
+            nickname::Event::InboundFailure { peer, error, .. } => {
+                log::warn!("Couldn't receive nickname of {peer}: {error}");
+            }
+            nickname::Event::Message {
+                message: request_response::Message::Response { .. },
+                ..
+            }
+            | nickname::Event::ResponseSent { .. } => {}
+        }
     }
 
-    pub fn send_message(&mut self, message: Message) -> Result<(), SendingError> {
-        if self.current_topic.is_none() {
-            return Err(SendingError::NoSubscribedTopic);
+    async fn handle_history_sync_event(&mut self, event: history_sync::Event) {
+        match event {
+            history_sync::Event::Message {
+                peer,
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+                ..
+            } => {
+                // only history of public topics this node takes part in is shared
+                let shared = self.topics.get(&request.topic).is_some()
+                    && !self.topics.is_private(&request.topic);
+
+                let records = match shared {
+                    true => self
+                        .history
+                        .last_public(&request.topic, request.limit.min(SYNC_LIMIT))
+                        .unwrap_or_else(|e| {
+                            log::error!("Couldn't read history: {e}");
+                            Vec::new()
+                        }),
+                    false => Vec::new(),
+                };
+
+                log::info!(
+                    "Sending {} messages of topic {} to {peer}",
+                    records.len(),
+                    request.topic
+                );
+
+                let response = HistoryResponse {
+                    topic: request.topic,
+                    records,
+                };
+
+                if self
+                    .swarm
+                    .behaviour_mut()
+                    .history_sync
+                    .send_response(channel, response)
+                    .is_err()
+                {
+                    log::warn!("Couldn't send history to {peer}, connection closed");
+                }
+            }
+            history_sync::Event::Message {
+                peer,
+                message: request_response::Message::Response { response, .. },
+                ..
+            } => {
+                if self.topics.get(&response.topic).is_none()
+                    || self.topics.is_private(&response.topic)
+                {
+                    return;
+                }
+
+                let topic = response.topic.clone();
+                let records = history_sync::merge(&self.history, response);
+
+                log::info!(
+                    "Got {} new messages of topic {topic} from {peer}",
+                    records.len()
+                );
+
+                if records.is_empty() {
+                    return;
+                }
+
+                let notification = Notification::SyncedHistory { topic, records };
+                notify(&self.notification_sender, notification).await;
+            }
+            history_sync::Event::OutboundFailure { peer, error, .. } => {
+                log::warn!("Couldn't get history from {peer}: {error}");
+            }
+            history_sync::Event::InboundFailure { peer, error, .. } => {
+                log::warn!("Couldn't send history to {peer}: {error}");
+            }
+            history_sync::Event::ResponseSent { .. } => {}
         }
+    }
 
-        let topic = self.current_topic.clone().unwrap();
+    /// Subscribes the topic and makes it active. Messages of the topic are encrypted with `key` if set
+    pub fn subscribe(
+        &mut self,
+        topic_name: impl Into<String>,
+        key: Option<TopicKey>,
+    ) -> Result<bool, TopicError> {
+        let topic_name = topic_name.into();
+        let previous = self.topics.get(&topic_name).cloned();
+        let topic = self.topics.insert(&topic_name, key);
+
+        // switching between public and private changes the topic on the wire
+        if let Some(previous) = previous.filter(|previous| previous.hash() != topic.hash()) {
+            self.swarm.behaviour_mut().gossipsub.unsubscribe(&previous);
+        }
 
-        let encoded_message = message
-            .encode_to_vec()
-            .map_err(|e| SendingError::CantEncodeMessage(e))?;
+        match self.swarm.behaviour_mut().gossipsub.subscribe(&topic) {
+            Ok(res) => Ok(res),
+            Err(e) => {
+                self.topics.remove(&topic_name);
+                Err(e.into())
+            }
+        }
+    }
+
+    pub fn unsubscribe(&mut self, topic_name: String) -> bool {
+        match self.topics.remove(&topic_name) {
+            Some(topic) => self.swarm.behaviour_mut().gossipsub.unsubscribe(&topic),
+            None => false,
+        }
+    }
 
+    /// Connected peers subscribed to the topic. Own messages are flood published,
+    /// so all of them get the message, not only the mesh peers
+    fn topic_peer_count(&self, hash: &gossipsub::TopicHash) -> usize {
         self.swarm
+            .behaviour()
+            .gossipsub
+            .all_peers()
+            .filter(|(_, topics)| topics.contains(&hash))
+            .count()
+    }
+
+    /// Publishes the message to `topic_name` or to the active topic if None
+    pub fn send_message(
+        &mut self,
+        topic_name: Option<&str>,
+        message: Message,
+    ) -> Result<SendOutcome, SendingError> {
+        let topic_name = match topic_name {
+            Some(topic_name) if self.topics.get(topic_name).is_some() => topic_name.to_string(),
+            Some(topic_name) => return Err(SendingError::NotSubscribedTo(topic_name.to_string())),
+            None => self
+                .topics
+                .active_name()
+                .ok_or(SendingError::NoSubscribedTopic)?
+                .to_string(),
+        };
+
+        let mut encoded_message = message
+            .encode_to_vec()
+            .map_err(SendingError::CantEncodeMessage)?;
+
+        if let Some(key) = self.topics.key(&topic_name) {
+            encoded_message = key.seal(&encoded_message)?;
+        }
+
+        let topic = self
+            .topics
+            .get(&topic_name)
+            .ok_or(SendingError::NoSubscribedTopic)?
+            .clone();
+
+        let recipients = self.topic_peer_count(&topic.hash());
+
+        let message_id = self
+            .swarm
             .behaviour_mut()
             .gossipsub
             .publish(topic, encoded_message)?;
 
-        Ok(())
+        if let Some(file) = &message.file {
+            self.file_transfer.share(file);
+        }
+
+        let record = HistoryRecord::new(
+            message_id.to_string(),
+            topic_name.clone(),
+            self.swarm.local_peer_id().to_string(),
+            &message,
+            self.topics.is_private(&topic_name),
+        );
+        if let Err(e) = self.history.insert(&record) {
+            log::error!("Couldn't save message {message_id} to history: {e}");
+        }
+
+        Ok(SendOutcome {
+            message_id: message_id.to_string(),
+            topic: topic_name,
+            recipients,
+        })
+    }
+}
+
+fn peer_infos(directory: &PeerDirectory, topics: &TopicRegistry) -> Vec<PeerInfo> {
+    let mut peers: Vec<PeerInfo> = directory
+        .online()
+        .map(|(peer_id, presence)| {
+            let mut peer_topics: Vec<String> = presence
+                .topics
+                .iter()
+                .map(|hash| match topics.name_of(hash) {
+                    Some(name) => name.to_string(),
+                    None => hash.to_string(),
+                })
+                .collect();
+            peer_topics.sort();
+
+            PeerInfo {
+                name: directory.name(peer_id),
+                peer_id: *peer_id,
+                connection: presence.connection,
+                rtt: presence.rtt,
+                topics: peer_topics,
+                addresses: presence.addresses.clone(),
+            }
+        })
+        .collect();
+
+    peers.sort_by_key(|peer| peer.name.to_string());
+    peers
+}
+
+async fn notify(notification_sender: &mpsc::Sender<Notification>, notification: Notification) {
+    // the client stops reading before the swarm shuts down
+    if notification_sender.is_closed() {
+        return;
+    }
+
+    if let Err(e) = notification_sender.send(notification).await {
+        log::error!("Couldn't send notification to client: {e}");
     }
 }
 
 #[cfg(feature = "quic")]
-fn build_swarm() -> anyhow::Result<Swarm<ChatBehaviour>> {
-    let swarm = libp2p::SwarmBuilder::with_new_identity()
+fn build_swarm(keypair: Keypair, config: &SwarmConfig) -> anyhow::Result<Swarm<ChatBehaviour>> {
+    let swarm = libp2p::SwarmBuilder::with_existing_identity(keypair)
         .with_tokio()
         .with_tcp(
             tcp::Config::default(),
@@ -239,7 +953,7 @@ fn build_swarm() -> anyhow::Result<Swarm<ChatBehaviour>> {
         )?
         .with_quic()
         .with_behaviour(|key| {
-            let behaviour = ChatBehaviour::build(key)?;
+            let behaviour = ChatBehaviour::build(key, config)?;
             Ok(behaviour)
         })?
         .build();
@@ -248,8 +962,8 @@ fn build_swarm() -> anyhow::Result<Swarm<ChatBehaviour>> {
 }
 
 #[cfg(not(feature = "quic"))]
-fn build_swarm() -> anyhow::Result<Swarm<ChatBehaviour>> {
-    let swarm = libp2p::SwarmBuilder::with_new_identity()
+fn build_swarm(keypair: Keypair, config: &SwarmConfig) -> anyhow::Result<Swarm<ChatBehaviour>> {
+    let swarm = libp2p::SwarmBuilder::with_existing_identity(keypair)
         .with_tokio()
         .with_tcp(
             tcp::Config::default(),
@@ -257,7 +971,7 @@ fn build_swarm() -> anyhow::Result<Swarm<ChatBehaviour>> {
             yamux::Config::default,
         )?
         .with_behaviour(|key| {
-            let behaviour = ChatBehaviour::build(key)?;
+            let behaviour = ChatBehaviour::build(key, config)?;
             Ok(behaviour)
         })?
         .build();
@@ -275,14 +989,30 @@ mod tests {
     #[tokio::test]
     async fn communication_test() -> anyhow::Result<()> {
         let (command_sender1, command_receiver1) = mpsc::channel::<Command>(20);
-        let (msg_sender1, _) = mpsc::channel::<(Message, ShortPeerId)>(20);
-
-        let mut sw1 = SwarmManager::build(msg_sender1, command_receiver1)?.with_topic("1test");
+        let (msg_sender1, _) = mpsc::channel::<Notification>(20);
+
+        let mut sw1 = SwarmManager::build(
+            Keypair::generate_ed25519(),
+            HistoryStore::in_memory()?,
+            Vec::new(),
+            SwarmConfig::default(),
+            msg_sender1,
+            command_receiver1,
+        )?
+        .with_topic("1test");
 
         let (_, command_receiver2) = mpsc::channel::<Command>(20);
-        let (msg_sender2, mut msg_receiver2) = mpsc::channel::<(Message, ShortPeerId)>(20);
-
-        let mut sw2 = SwarmManager::build(msg_sender2, command_receiver2)?.with_topic("1test");
+        let (msg_sender2, mut msg_receiver2) = mpsc::channel::<Notification>(20);
+
+        let mut sw2 = SwarmManager::build(
+            Keypair::generate_ed25519(),
+            HistoryStore::in_memory()?,
+            Vec::new(),
+            SwarmConfig::default(),
+            msg_sender2,
+            command_receiver2,
+        )?
+        .with_topic("1test");
 
         for _ in 0..24 {
             let ev = sw1.swarm.select_next_some().await;
@@ -292,7 +1022,7 @@ mod tests {
         }
 
         let msg = Message::build(Some("Test".to_string()), None).await;
-        let (command, _) = Command::new_send_message(msg.clone());
+        let (command, _) = Command::new_send_message(None, msg.clone());
 
         command_sender1.send(command).await?;
 
@@ -302,18 +1032,28 @@ mod tests {
         let ev = sw2.swarm.select_next_some().await;
         sw2.handle_event(ev).await;
 
-        let (received_msg, _) = msg_receiver2.try_recv()?;
+        let Notification::Message(received) = msg_receiver2.try_recv()? else {
+            panic!("Expected message notification");
+        };
 
-        assert_eq!(received_msg, msg);
+        assert_eq!(received.topic, "1test");
+        assert_eq!(received.msg, msg);
 
         Ok(())
     }
     #[tokio::test]
     async fn send_message_without_topic() -> anyhow::Result<()> {
         let (_, command_receiver) = mpsc::channel::<Command>(20);
-        let (msg_sender, _) = mpsc::channel::<(Message, ShortPeerId)>(20);
+        let (msg_sender, _) = mpsc::channel::<Notification>(20);
 
-        let mut sw = SwarmManager::build(msg_sender, command_receiver)?;
+        let mut sw = SwarmManager::build(
+            Keypair::generate_ed25519(),
+            HistoryStore::in_memory()?,
+            Vec::new(),
+            SwarmConfig::default(),
+            msg_sender,
+            command_receiver,
+        )?;
 
         for _ in 0..8 {
             let ev = sw.swarm.select_next_some().await;
@@ -321,12 +1061,9 @@ mod tests {
         }
 
         let msg = Message::build(Some("Test".to_string()), None).await;
-        let res = sw.send_message(msg);
+        let res = sw.send_message(None, msg);
 
-        assert_eq!(
-            res.unwrap_err().to_string(),
-            SendingError::NoSubscribedTopic.to_string()
-        );
+        assert!(matches!(res, Err(SendingError::NoSubscribedTopic)));
 
         Ok(())
     }
@@ -334,9 +1071,17 @@ mod tests {
     #[tokio::test]
     async fn send_message_without_connected_peers() -> anyhow::Result<()> {
         let (_, command_receiver) = mpsc::channel::<Command>(20);
-        let (msg_sender, _) = mpsc::channel::<(Message, ShortPeerId)>(20);
+        let (msg_sender, _) = mpsc::channel::<Notification>(20);
 
-        let mut sw = SwarmManager::build(msg_sender, command_receiver)?.with_topic("3test");
+        let mut sw = SwarmManager::build(
+            Keypair::generate_ed25519(),
+            HistoryStore::in_memory()?,
+            Vec::new(),
+            SwarmConfig::default(),
+            msg_sender,
+            command_receiver,
+        )?
+        .with_topic("3test");
 
         for _ in 0..8 {
             let ev = sw.swarm.select_next_some().await;
@@ -344,12 +1089,12 @@ mod tests {
         }
 
         let msg = Message::build(Some("Test".to_string()), None).await;
-        let res = sw.send_message(msg);
+        let res = sw.send_message(None, msg);
 
-        assert_eq!(
-            res.unwrap_err().to_string(),
-            SendingError::Other(PublishError::InsufficientPeers).to_string()
-        );
+        assert!(matches!(
+            res,
+            Err(SendingError::Other(PublishError::InsufficientPeers))
+        ));
 
         Ok(())
     }
@@ -357,23 +1102,34 @@ mod tests {
     #[tokio::test]
     async fn subscribe_command() -> anyhow::Result<()> {
         let (command_sender, command_receiver) = mpsc::channel::<Command>(20);
-        let (msg_sender, _) = mpsc::channel::<(Message, ShortPeerId)>(20);
+        let (msg_sender, _) = mpsc::channel::<Notification>(20);
 
-        let mut sw = SwarmManager::build(msg_sender, command_receiver)?;
+        let mut sw = SwarmManager::build(
+            Keypair::generate_ed25519(),
+            HistoryStore::in_memory()?,
+            Vec::new(),
+            SwarmConfig::default(),
+            msg_sender,
+            command_receiver,
+        )?;
 
         for _ in 0..8 {
             let ev = sw.swarm.select_next_some().await;
             sw.handle_event(ev).await;
         }
 
-        let (command, _) = Command::new_subscribe("4test".to_string());
+        let (command, response_receiver) = Command::new_subscribe("4test".to_string(), None);
 
         command_sender.send(command).await?;
 
         let command = sw.command_receiver.recv().await;
         sw.handle_command(command).await;
 
-        assert_eq!(sw.current_topic.unwrap().to_string(), "4test".to_string());
+        assert_eq!(sw.topics.active_name(), Some("4test"));
+        assert_eq!(
+            response_receiver.await?.ok(),
+            Some(SubscribeOutcome::Subscribed)
+        );
 
         Ok(())
     }
@@ -381,22 +1137,34 @@ mod tests {
     #[tokio::test]
     async fn unsubscribe_command() -> anyhow::Result<()> {
         let (command_sender, command_receiver) = mpsc::channel::<Command>(20);
-        let (msg_sender, _) = mpsc::channel::<(Message, ShortPeerId)>(20);
-        let mut sw = SwarmManager::build(msg_sender, command_receiver)?.with_topic("5test");
+        let (msg_sender, _) = mpsc::channel::<Notification>(20);
+        let mut sw = SwarmManager::build(
+            Keypair::generate_ed25519(),
+            HistoryStore::in_memory()?,
+            Vec::new(),
+            SwarmConfig::default(),
+            msg_sender,
+            command_receiver,
+        )?
+        .with_topic("5test");
 
         for _ in 0..8 {
             let ev = sw.swarm.select_next_some().await;
             sw.handle_event(ev).await;
         }
 
-        let (command, _) = Command::new_unsubscribe("5test".to_string());
+        let (command, response_receiver) = Command::new_unsubscribe("5test".to_string());
 
         command_sender.send(command).await?;
 
         let command = sw.command_receiver.recv().await;
         sw.handle_command(command).await;
 
-        assert!(sw.current_topic.is_none());
+        assert!(sw.topics.active_name().is_none());
+        assert_eq!(
+            response_receiver.await?.ok(),
+            Some(UnsubscribeOutcome { active: None })
+        );
 
         Ok(())
     }
@@ -0,0 +1,164 @@
+use crate::models::common::topic_key::TopicKey;
+use libp2p::gossipsub::{IdentTopic, TopicHash};
+use std::collections::BTreeMap;
+
+/// Every topic the swarm is subscribed to and the active one,
+/// which receives messages sent without an explicit topic
+#[derive(Debug, Default)]
+pub struct TopicRegistry {
+    topics: BTreeMap<String, Entry>,
+    active: Option<String>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    topic: IdentTopic,
+    /// set for private topics, whose messages are encrypted
+    key: Option<TopicKey>,
+}
+
+impl TopicRegistry {
+    /// Registers the topic, replacing the previous key if any, and makes it active.
+    /// Private topics use the hashed name from the key on the wire
+    pub fn insert(&mut self, topic_name: &str, key: Option<TopicKey>) -> IdentTopic {
+        let topic = match &key {
+            Some(key) => IdentTopic::new(key.wire_name()),
+            None => IdentTopic::new(topic_name),
+        };
+
+        self.topics.insert(
+            topic_name.to_string(),
+            Entry {
+                topic: topic.clone(),
+                key,
+            },
+        );
+
+        self.active = Some(topic_name.to_string());
+
+        topic
+    }
+
+    /// Removes the topic. If it was the active one, another subscribed topic becomes active
+    pub fn remove(&mut self, topic_name: &str) -> Option<IdentTopic> {
+        let entry = self.topics.remove(topic_name)?;
+
+        if self.active.as_deref() == Some(topic_name) {
+            self.active = self.topics.keys().next().cloned();
+        }
+
+        Some(entry.topic)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.topics.keys().cloned().collect()
+    }
+
+    pub fn get(&self, topic_name: &str) -> Option<&IdentTopic> {
+        self.topics.get(topic_name).map(|entry| &entry.topic)
+    }
+
+    pub fn key(&self, topic_name: &str) -> Option<&TopicKey> {
+        self.topics
+            .get(topic_name)
+            .and_then(|entry| entry.key.as_ref())
+    }
+
+    pub fn is_private(&self, topic_name: &str) -> bool {
+        self.key(topic_name).is_some()
+    }
+
+    /// Makes a subscribed topic active, returns false if swarm is not subscribed to it
+    pub fn switch(&mut self, topic_name: &str) -> bool {
+        if !self.topics.contains_key(topic_name) {
+            return false;
+        }
+
+        self.active = Some(topic_name.to_string());
+
+        true
+    }
+
+    pub fn active_name(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    pub fn name_of(&self, hash: &TopicHash) -> Option<&str> {
+        self.topics
+            .iter()
+            .find(|(_, entry)| &entry.topic.hash() == hash)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_makes_topic_active() {
+        let mut registry = TopicRegistry::default();
+
+        registry.insert("ops", None);
+        registry.insert("dev", None);
+
+        assert_eq!(registry.active_name(), Some("dev"));
+        assert!(registry.get("ops").is_some());
+    }
+
+    #[test]
+    fn remove_inactive_topic_keeps_active() {
+        let mut registry = TopicRegistry::default();
+
+        registry.insert("ops", None);
+        registry.insert("dev", None);
+
+        assert!(registry.remove("ops").is_some());
+        assert_eq!(registry.active_name(), Some("dev"));
+        assert!(registry.remove("ops").is_none());
+    }
+
+    #[test]
+    fn remove_active_topic_falls_back() {
+        let mut registry = TopicRegistry::default();
+
+        registry.insert("ops", None);
+        registry.insert("dev", None);
+        registry.remove("dev");
+
+        assert_eq!(registry.active_name(), Some("ops"));
+
+        registry.remove("ops");
+
+        assert!(registry.active_name().is_none());
+    }
+
+    #[test]
+    fn switch_and_lookup() {
+        let mut registry = TopicRegistry::default();
+
+        let ops = registry.insert("ops", None);
+        registry.insert("dev", None);
+
+        assert!(!registry.switch("random"));
+        assert!(registry.switch("ops"));
+        assert_eq!(registry.active_name(), Some("ops"));
+        assert_eq!(registry.name_of(&ops.hash()), Some("ops"));
+    }
+
+    #[test]
+    fn private_topic_uses_wire_name() {
+        let mut registry = TopicRegistry::default();
+
+        let key = TopicKey::derive("ops", "secret").unwrap();
+        let wire_name = key.wire_name().to_string();
+        let ops = registry.insert("ops", Some(key));
+
+        assert_eq!(ops.to_string(), wire_name);
+        assert!(registry.is_private("ops"));
+        assert_eq!(registry.name_of(&ops.hash()), Some("ops"));
+
+        registry.insert("ops", None);
+        assert!(!registry.is_private("ops"));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod state;
+pub mod terminal_ui;
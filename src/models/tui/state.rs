@@ -0,0 +1,707 @@
+use crate::models::client::tracker::{CommandId, Tracked};
+use crate::models::client::{Exit, Output, format_notification};
+use crate::models::common::notification::Notification;
+use crate::models::common::peer_name::PeerName;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+const MAX_SCROLLBACK: usize = 1000;
+const MAX_INPUT_HISTORY: usize = 100;
+
+/// Where lines of a pane come from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaneKind {
+    /// command responses and peer notifications
+    Status,
+    /// direct messages from all peers
+    Direct,
+    Topic(String),
+}
+
+/// Delivery state shown after each message typed in the terminal UI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageState {
+    Pending,
+    Sent,
+    Failed,
+}
+
+/// What the UI does once a command it sent gets a response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Sent {
+    /// message shown in the pane as the line with this number
+    Message {
+        kind: PaneKind,
+        line: usize,
+        text: String,
+    },
+    /// opens the pane of the joined topic
+    Subscribe(String),
+    /// closes the pane of the left topic
+    Unsubscribe(String),
+    /// shows the pane of the topic
+    Switch(String),
+    /// pane switched with Tab, only failures are shown
+    Background,
+}
+
+#[derive(Debug)]
+pub struct Pane {
+    pub kind: PaneKind,
+    lines: VecDeque<String>,
+    /// number of lines ever pushed, the first kept line has number `pushed - lines.len()`
+    pushed: usize,
+    /// number of lines scrolled up from the bottom
+    scroll: usize,
+    /// new lines arrived while the pane wasn't shown
+    pub unread: bool,
+}
+
+/// Everything the terminal UI shows, changed only by key presses, responses and notifications
+#[derive(Debug)]
+pub struct UiState {
+    panes: Vec<Pane>,
+    active: usize,
+    pub input: InputLine,
+    /// connected peers in the order they joined
+    peers: Vec<PeerName>,
+    /// received and total bytes of files being downloaded
+    downloads: BTreeMap<String, (u64, u64)>,
+    /// commands still waiting for SwarmManager
+    pending: HashMap<CommandId, Sent>,
+    /// set when the UI should stop
+    pub exit: Option<Exit>,
+}
+
+/// Single line editor with history of submitted lines
+#[derive(Debug, Default)]
+pub struct InputLine {
+    chars: Vec<char>,
+    cursor: usize,
+    history: VecDeque<String>,
+    /// position in `history` while browsing it, None when editing a new line
+    browsing: Option<usize>,
+}
+
+impl Pane {
+    fn new(kind: PaneKind) -> Self {
+        Self {
+            kind,
+            lines: VecDeque::new(),
+            pushed: 0,
+            scroll: 0,
+            unread: false,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match &self.kind {
+            PaneKind::Status => "status".to_string(),
+            PaneKind::Direct => "dm".to_string(),
+            PaneKind::Topic(topic) => format!("#{topic}"),
+        }
+    }
+
+    /// Last `height` lines above the scroll position
+    pub fn visible(&self, height: usize) -> impl Iterator<Item = &String> {
+        let end = self.lines.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(height);
+
+        self.lines.range(start..end)
+    }
+
+    pub fn scrolled(&self) -> bool {
+        self.scroll > 0
+    }
+
+    fn push(&mut self, line: String) {
+        self.lines.push_back(line);
+        self.pushed += 1;
+
+        if self.lines.len() > MAX_SCROLLBACK {
+            self.lines.pop_front();
+        }
+
+        // keep the shown lines in place while the user reads older ones
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.lines.len());
+        }
+    }
+
+    /// Replaces the line with the number, returns false if it is out of scrollback
+    fn replace(&mut self, number: usize, new: String) -> bool {
+        let first = self.pushed - self.lines.len();
+
+        match number
+            .checked_sub(first)
+            .and_then(|index| self.lines.get_mut(index))
+        {
+            Some(line) => {
+                *line = new;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Default for UiState {
+    fn default() -> Self {
+        Self {
+            panes: vec![Pane::new(PaneKind::Status), Pane::new(PaneKind::Direct)],
+            active: 0,
+            input: InputLine::default(),
+            peers: Vec::new(),
+            downloads: BTreeMap::new(),
+            pending: HashMap::new(),
+            exit: None,
+        }
+    }
+}
+
+impl UiState {
+    pub fn panes(&self) -> &[Pane] {
+        &self.panes
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn active_pane(&self) -> &Pane {
+        &self.panes[self.active]
+    }
+
+    /// Topic of the shown pane, messages typed without `#topic` go there
+    pub fn active_topic(&self) -> Option<&str> {
+        match &self.active_pane().kind {
+            PaneKind::Topic(topic) => Some(topic),
+            _ => None,
+        }
+    }
+
+    pub fn peers(&self) -> &[PeerName] {
+        &self.peers
+    }
+
+    pub fn downloads(&self) -> impl Iterator<Item = (&String, &(u64, u64))> {
+        self.downloads.iter()
+    }
+
+    pub fn on_notification(&mut self, notification: Notification) {
+        let kind = match &notification {
+            Notification::Message(msg) => PaneKind::Topic(msg.topic.clone()),
+            Notification::SyncedHistory { topic, .. }
+            | Notification::UndecryptableMessage { topic, .. } => PaneKind::Topic(topic.clone()),
+            Notification::DirectMessage { .. } => PaneKind::Direct,
+            Notification::PeerJoined { name, .. } => {
+                self.peers.push(name.clone());
+                PaneKind::Status
+            }
+            Notification::PeerLeft { name, .. } => {
+                self.peers.retain(|peer| peer != name);
+                PaneKind::Status
+            }
+            Notification::NickChanged { old, new, .. } => {
+                if let Some(peer) = self.peers.iter_mut().find(|peer| **peer == *old) {
+                    *peer = new.clone();
+                }
+                PaneKind::Status
+            }
+            Notification::DownloadProgress {
+                name,
+                received,
+                size,
+            } => {
+                self.downloads.insert(name.clone(), (*received, *size));
+                return;
+            }
+            Notification::DownloadFinished { name, .. }
+            | Notification::DownloadFailed { name, .. } => {
+                self.downloads.remove(name);
+                PaneKind::Status
+            }
+            Notification::UploadFinished { .. } => PaneKind::Status,
+        };
+
+        self.push(kind, format_notification(notification));
+    }
+
+    /// Adds lines to the pane, creating it for topics that don't have one yet
+    pub fn push(&mut self, kind: PaneKind, lines: Vec<String>) {
+        let index = self.open(kind);
+        let pane = &mut self.panes[index];
+
+        for line in lines {
+            pane.push(line);
+        }
+
+        pane.unread = index != self.active;
+    }
+
+    /// Shows the message typed by the user with its delivery state
+    pub fn outgoing(&mut self, kind: PaneKind, text: &str, state: MessageState) {
+        self.push(kind, vec![outgoing_line(text, state)]);
+    }
+
+    /// Shows the message as pending until its response comes
+    pub fn outgoing_pending(&mut self, id: CommandId, kind: PaneKind, text: String) {
+        self.outgoing(kind.clone(), &text, MessageState::Pending);
+
+        let index = self.open(kind.clone());
+        let line = self.panes[index].pushed - 1;
+        self.pending.insert(id, Sent::Message { kind, line, text });
+    }
+
+    /// Remembers what to do once the command gets a response
+    pub fn sent(&mut self, id: CommandId, sent: Sent) {
+        self.pending.insert(id, sent);
+    }
+
+    /// Applies the response to the command it answers, or shows it in the shown pane
+    pub fn on_response(&mut self, tracked: Tracked) {
+        let lines = Output::Text.tracked(&tracked);
+
+        let response = match tracked {
+            Tracked::Pending { id, .. } => {
+                // pending messages already say so
+                if !matches!(
+                    self.pending.get(&id),
+                    Some(Sent::Message { .. } | Sent::Background)
+                ) {
+                    self.push_active(lines);
+                }
+                return;
+            }
+            Tracked::Response(response) => response,
+        };
+
+        match self.pending.remove(&response.id) {
+            Some(Sent::Message { kind, line, text }) => {
+                let state = match response.failed {
+                    true => MessageState::Failed,
+                    false => MessageState::Sent,
+                };
+
+                let index = self.open(kind.clone());
+
+                if !self.panes[index].replace(line, outgoing_line(&text, state)) {
+                    self.outgoing(kind.clone(), &text, state);
+                }
+
+                if response.failed {
+                    self.push(
+                        kind,
+                        response
+                            .lines
+                            .iter()
+                            .map(|line| format!("> {line}"))
+                            .collect(),
+                    );
+                }
+            }
+            Some(Sent::Subscribe(topic)) => {
+                // a pane is opened only for a topic the node joined
+                if !response.failed {
+                    self.subscribed(&topic);
+                }
+                self.push_active(lines);
+            }
+            Some(Sent::Unsubscribe(topic)) => {
+                if !response.failed {
+                    self.unsubscribed(&topic);
+                }
+                self.push(PaneKind::Status, lines);
+            }
+            Some(Sent::Switch(topic)) => {
+                if !response.failed {
+                    self.show_topic(&topic);
+                }
+                self.push_active(lines);
+            }
+            Some(Sent::Background) if response.failed => self.push(PaneKind::Status, lines),
+            Some(Sent::Background) => {}
+            None => self.push_active(lines),
+        }
+    }
+
+    /// Adds lines to the shown pane
+    pub fn push_active(&mut self, lines: Vec<String>) {
+        self.push(self.active_pane().kind.clone(), lines);
+    }
+
+    /// Opens the pane of the topic and shows it
+    pub fn subscribed(&mut self, topic: &str) {
+        self.active = self.open(PaneKind::Topic(topic.to_string()));
+        self.panes[self.active].unread = false;
+    }
+
+    pub fn unsubscribed(&mut self, topic: &str) {
+        let kind = PaneKind::Topic(topic.to_string());
+
+        if let Some(index) = self.panes.iter().position(|pane| pane.kind == kind) {
+            self.panes.remove(index);
+            // line numbers of the closed pane would point into a new one
+            self.pending.retain(
+                |_, sent| !matches!(sent, Sent::Message { kind: closed, .. } if *closed == kind),
+            );
+
+            if self.active >= index {
+                self.active = self.active.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Shows the pane of the topic if there is one
+    pub fn show_topic(&mut self, topic: &str) {
+        let kind = PaneKind::Topic(topic.to_string());
+
+        if let Some(index) = self.panes.iter().position(|pane| pane.kind == kind) {
+            self.show(index);
+        }
+    }
+
+    /// Shows the next pane, returns its topic
+    pub fn next_pane(&mut self) -> Option<&str> {
+        self.show((self.active + 1) % self.panes.len());
+        self.active_topic()
+    }
+
+    /// Shows the previous pane, returns its topic
+    pub fn prev_pane(&mut self) -> Option<&str> {
+        self.show((self.active + self.panes.len() - 1) % self.panes.len());
+        self.active_topic()
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        let pane = &mut self.panes[self.active];
+        pane.scroll = (pane.scroll + lines).min(pane.lines.len().saturating_sub(1));
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        let pane = &mut self.panes[self.active];
+        pane.scroll = pane.scroll.saturating_sub(lines);
+    }
+
+    fn show(&mut self, index: usize) {
+        self.active = index;
+        self.panes[index].unread = false;
+    }
+
+    fn open(&mut self, kind: PaneKind) -> usize {
+        match self.panes.iter().position(|pane| pane.kind == kind) {
+            Some(index) => index,
+            None => {
+                self.panes.push(Pane::new(kind));
+                self.panes.len() - 1
+            }
+        }
+    }
+}
+
+fn outgoing_line(text: &str, state: MessageState) -> String {
+    let state = match state {
+        MessageState::Pending => "pending",
+        MessageState::Sent => "sent",
+        MessageState::Failed => "failed",
+    };
+
+    format!("you: {text} [{state}]")
+}
+
+impl InputLine {
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    /// Cursor position in characters
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.chars.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.chars.len());
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.chars.len();
+    }
+
+    /// Removes the word before the cursor
+    pub fn delete_word(&mut self) {
+        let end = self.cursor;
+
+        while self.cursor > 0 && self.chars[self.cursor - 1] == ' ' {
+            self.cursor -= 1;
+        }
+        while self.cursor > 0 && self.chars[self.cursor - 1] != ' ' {
+            self.cursor -= 1;
+        }
+
+        self.chars.drain(self.cursor..end);
+    }
+
+    /// Shows the previous submitted line
+    pub fn history_prev(&mut self) {
+        let index = match self.browsing {
+            Some(index) => index.saturating_sub(1),
+            None if self.history.is_empty() => return,
+            None => self.history.len() - 1,
+        };
+
+        self.browsing = Some(index);
+        self.set(self.history[index].clone());
+    }
+
+    /// Shows the next submitted line or an empty one after the last
+    pub fn history_next(&mut self) {
+        let Some(index) = self.browsing else {
+            return;
+        };
+
+        match self.history.get(index + 1).cloned() {
+            Some(line) => {
+                self.browsing = Some(index + 1);
+                self.set(line);
+            }
+            None => {
+                self.browsing = None;
+                self.set(String::new());
+            }
+        }
+    }
+
+    /// Clears the line and remembers it in history, returns None for blank lines
+    pub fn submit(&mut self) -> Option<String> {
+        let line = self.text();
+        self.set(String::new());
+        self.browsing = None;
+
+        if line.trim().is_empty() {
+            return None;
+        }
+
+        if self.history.back() != Some(&line) {
+            self.history.push_back(line.clone());
+        }
+        if self.history.len() > MAX_INPUT_HISTORY {
+            self.history.pop_front();
+        }
+
+        Some(line)
+    }
+
+    fn set(&mut self, line: String) {
+        self.chars = line.chars().collect();
+        self.cursor = self.chars.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::client::tracker::Response;
+    use libp2p::PeerId;
+
+    fn peer(nick: &str) -> PeerName {
+        PeerName::new("x7Fq9a".parse().unwrap(), Some(nick))
+    }
+
+    #[test]
+    fn input_editing() {
+        let mut input = InputLine::default();
+
+        "helo world".chars().for_each(|c| input.insert(c));
+        (0..7).for_each(|_| input.left());
+        input.insert('l');
+        assert_eq!(input.text(), "hello world");
+        assert_eq!(input.cursor(), 4);
+
+        input.end();
+        input.delete_word();
+        assert_eq!(input.text(), "hello ");
+
+        input.home();
+        input.delete();
+        input.backspace();
+        assert_eq!(input.text(), "ello ");
+    }
+
+    #[test]
+    fn input_history() {
+        let mut input = InputLine::default();
+
+        for line in ["first", "second", " "] {
+            line.chars().for_each(|c| input.insert(c));
+            input.submit();
+        }
+
+        input.history_prev();
+        assert_eq!(input.text(), "second");
+        input.history_prev();
+        input.history_prev();
+        assert_eq!(input.text(), "first");
+        input.history_next();
+        assert_eq!(input.text(), "second");
+        input.history_next();
+        assert_eq!(input.text(), "");
+    }
+
+    #[test]
+    fn panes_follow_topics() {
+        let mut state = UiState::default();
+
+        state.subscribed("ops");
+        assert_eq!(state.active_topic(), Some("ops"));
+
+        state.push(PaneKind::Topic("dev".to_string()), vec!["hi".to_string()]);
+        assert_eq!(state.active_topic(), Some("ops"));
+        assert!(state.panes().last().unwrap().unread);
+
+        assert_eq!(state.next_pane(), Some("dev"));
+        assert!(!state.active_pane().unread);
+        assert_eq!(state.next_pane(), None);
+        assert_eq!(state.prev_pane(), Some("dev"));
+
+        state.unsubscribed("dev");
+        assert_eq!(state.active_topic(), Some("ops"));
+        assert_eq!(state.panes().len(), 3);
+    }
+
+    #[test]
+    fn scroll_keeps_view() {
+        let mut state = UiState::default();
+        let lines = |n: usize| (0..n).map(|i| i.to_string()).collect::<Vec<_>>();
+
+        state.push_active(lines(10));
+        state.scroll_up(3);
+        state.push_active(lines(1));
+
+        let shown: Vec<&String> = state.active_pane().visible(2).collect();
+        assert_eq!(shown, ["5", "6"]);
+
+        state.scroll_down(100);
+        let shown: Vec<&String> = state.active_pane().visible(2).collect();
+        assert_eq!(shown, ["9", "0"]);
+    }
+
+    #[test]
+    fn sidebar_and_downloads() {
+        let mut state = UiState::default();
+
+        state.on_notification(Notification::PeerJoined {
+            name: peer("alice"),
+            peer_id: PeerId::random(),
+        });
+        state.on_notification(Notification::PeerJoined {
+            name: peer("bob"),
+            peer_id: PeerId::random(),
+        });
+        state.on_notification(Notification::NickChanged {
+            peer_id: PeerId::random(),
+            old: peer("bob"),
+            new: peer("carol"),
+            conflicts: Vec::new(),
+        });
+        state.on_notification(Notification::PeerLeft {
+            name: peer("alice"),
+            peer_id: PeerId::random(),
+        });
+        assert_eq!(state.peers(), [peer("carol")]);
+
+        state.on_notification(Notification::DownloadProgress {
+            name: "a.txt".to_string(),
+            received: 5,
+            size: 10,
+        });
+        assert_eq!(state.downloads().count(), 1);
+
+        state.on_notification(Notification::DownloadFailed {
+            name: "a.txt".to_string(),
+            reason: "gone".to_string(),
+        });
+        assert_eq!(state.downloads().count(), 0);
+    }
+
+    fn response(id: CommandId, lines: &[&str], failed: bool) -> Tracked {
+        Tracked::Response(Response {
+            id,
+            command: "message 'hi'".to_string(),
+            lines: lines.iter().map(|line| line.to_string()).collect(),
+            result: serde_json::Value::Null,
+            failed,
+            late: true,
+        })
+    }
+
+    #[test]
+    fn pending_message_is_updated_in_place() {
+        let mut state = UiState::default();
+        let ops = PaneKind::Topic("ops".to_string());
+
+        state.subscribed("ops");
+        state.outgoing_pending(3, ops.clone(), "hi".to_string());
+        state.outgoing_pending(4, ops.clone(), "hi".to_string());
+        state.push(ops, vec!["bob: hey".to_string()]);
+
+        state.on_response(response(
+            3,
+            &["No peers are subscribed to the topic yet"],
+            true,
+        ));
+        state.on_response(response(4, &["Message sent"], false));
+
+        let shown: Vec<&String> = state.active_pane().visible(4).collect();
+        assert_eq!(
+            shown,
+            [
+                "you: hi [failed]",
+                "you: hi [sent]",
+                "bob: hey",
+                "> No peers are subscribed to the topic yet"
+            ]
+        );
+    }
+
+    #[test]
+    fn panes_follow_responses() {
+        let mut state = UiState::default();
+
+        state.sent(1, Sent::Subscribe("ops".to_string()));
+        state.sent(2, Sent::Subscribe("dev".to_string()));
+        state.on_response(response(1, &["Subscribed to ops"], false));
+        state.on_response(response(2, &["Invalid topic"], true));
+
+        assert_eq!(state.active_topic(), Some("ops"));
+        assert_eq!(state.panes().len(), 3);
+
+        state.sent(3, Sent::Unsubscribe("ops".to_string()));
+        state.on_response(response(3, &["Unsubscribed from ops"], false));
+        assert_eq!(state.panes().len(), 2);
+    }
+}
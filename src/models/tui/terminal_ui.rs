@@ -0,0 +1,260 @@
+use crate::models::client::signals::Signals;
+use crate::models::client::{Exit, Session, available_commands, format_error};
+use crate::models::command_line::parser::{self, UserCommand};
+use crate::models::node::handle::NodeEvents;
+use crate::models::tui::state::{PaneKind, Sent, UiState};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use libp2p::futures::StreamExt;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, Paragraph, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+
+const SIDEBAR_WIDTH: u16 = 30;
+const PAGE: usize = 10;
+const INPUT_PROMPT: &str = "> ";
+
+/// Full-screen client: a pane per topic, peer sidebar, status bar and input line.
+/// Uses the same Session as the line mode, so every command works the same way
+pub struct TerminalUi {
+    session: Session,
+    events: NodeEvents,
+    state: UiState,
+}
+
+impl TerminalUi {
+    pub fn new(session: Session, events: NodeEvents) -> Self {
+        Self {
+            session,
+            events,
+            state: UiState::default(),
+        }
+    }
+
+    /// Runs until the user quits or a signal comes
+    pub async fn run(mut self, mut signals: Signals) -> io::Result<Exit> {
+        let mut terminal = ratatui::init();
+
+        let res = self.event_loop(&mut terminal, &mut signals).await;
+
+        ratatui::restore();
+        res
+    }
+
+    async fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        signals: &mut Signals,
+    ) -> io::Result<Exit> {
+        let mut events = EventStream::new();
+
+        self.state.push(PaneKind::Status, available_commands());
+        self.state.push(
+            PaneKind::Status,
+            vec!["Messages typed in a topic pane are sent to its topic".to_string()],
+        );
+
+        loop {
+            if let Some(exit) = self.state.exit {
+                return Ok(exit);
+            }
+
+            terminal.draw(|frame| draw(frame, &self.state))?;
+
+            tokio::select! {
+                event = events.next() => match event {
+                    Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => self.on_key(key).await,
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e),
+                    None => return Ok(Exit::Quit),
+                },
+                notification = self.events.recv() => match notification {
+                    Some(notification) => self.state.on_notification(notification),
+                    None => return Ok(Exit::Quit),
+                },
+                Some(tracked) = self.session.response() => self.state.on_response(tracked),
+                exit = signals.recv() => return Ok(exit),
+            }
+        }
+    }
+
+    async fn on_key(&mut self, key: KeyEvent) {
+        let input = &mut self.state.input;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key.code {
+            KeyCode::Esc => self.state.exit = Some(Exit::Quit),
+            KeyCode::Char('c') if ctrl => self.state.exit = Some(Exit::Interrupted),
+            KeyCode::Char('a') if ctrl => input.home(),
+            KeyCode::Char('e') if ctrl => input.end(),
+            KeyCode::Char('w') if ctrl => input.delete_word(),
+            KeyCode::Char(c) => input.insert(c),
+            KeyCode::Backspace => input.backspace(),
+            KeyCode::Delete => input.delete(),
+            KeyCode::Left => input.left(),
+            KeyCode::Right => input.right(),
+            KeyCode::Home => input.home(),
+            KeyCode::End => input.end(),
+            KeyCode::Up => input.history_prev(),
+            KeyCode::Down => input.history_next(),
+            KeyCode::PageUp => self.state.scroll_up(PAGE),
+            KeyCode::PageDown => self.state.scroll_down(PAGE),
+            KeyCode::Tab => {
+                let topic = self.state.next_pane().map(str::to_string);
+                self.switch(topic).await;
+            }
+            KeyCode::BackTab => {
+                let topic = self.state.prev_pane().map(str::to_string);
+                self.switch(topic).await;
+            }
+            KeyCode::Enter => {
+                if let Some(line) = input.submit() {
+                    self.submit(line).await;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Makes the topic of the shown pane active, so `msg` without `#topic` goes there as well
+    async fn switch(&mut self, topic: Option<String>) {
+        if let Some(topic) = topic {
+            let id = self.session.switch(topic).await;
+            self.state.sent(id, Sent::Background);
+        }
+    }
+
+    async fn submit(&mut self, line: String) {
+        let cmd = match parser::parse(&line) {
+            Ok(cmd) => cmd,
+            Err(e) => {
+                self.state.push_active(format_error(&e));
+                return;
+            }
+        };
+
+        match cmd {
+            UserCommand::Quit => self.state.exit = Some(Exit::Quit),
+            UserCommand::Msg(topic, text) => {
+                let topic = topic.or_else(|| self.state.active_topic().map(str::to_string));
+                let id = self.session.publish(topic.clone(), text.clone()).await;
+
+                let kind = match topic {
+                    Some(topic) => PaneKind::Topic(topic),
+                    None => self.state.active_pane().kind.clone(),
+                };
+
+                self.state.outgoing_pending(id, kind, text);
+            }
+            UserCommand::Subscribe(topic, key) => {
+                match self.session.subscribe(topic.clone(), key).await {
+                    Ok(id) => self.state.sent(id, Sent::Subscribe(topic)),
+                    Err(lines) => self.state.push_active(lines),
+                }
+            }
+            UserCommand::Unsubscribe(topic) => {
+                let id = self.session.unsubscribe(topic.clone()).await;
+                self.state.sent(id, Sent::Unsubscribe(topic));
+            }
+            UserCommand::Switch(topic) => {
+                let id = self.session.switch(topic.clone()).await;
+                self.state.sent(id, Sent::Switch(topic));
+            }
+            cmd => {
+                let lines = self.session.execute(cmd).await;
+                self.state.push_active(lines);
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, state: &UiState) {
+    let [tabs, body, status, input] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(3),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let [scrollback, sidebar] =
+        Layout::horizontal([Constraint::Min(10), Constraint::Length(SIDEBAR_WIDTH)]).areas(body);
+
+    draw_tabs(frame, state, tabs);
+    draw_scrollback(frame, state, scrollback);
+    draw_sidebar(frame, state, sidebar);
+    draw_status(frame, state, status);
+    draw_input(frame, state, input);
+}
+
+fn draw_tabs(frame: &mut Frame, state: &UiState, area: Rect) {
+    let titles = state.panes().iter().map(|pane| match pane.unread {
+        true => format!("{}*", pane.title()),
+        false => pane.title(),
+    });
+
+    let tabs = Tabs::new(titles)
+        .select(state.active())
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+    frame.render_widget(tabs, area);
+}
+
+fn draw_scrollback(frame: &mut Frame, state: &UiState, area: Rect) {
+    let pane = state.active_pane();
+
+    let title = match pane.scrolled() {
+        true => format!("{} (scrolled, PgDn to return)", pane.title()),
+        false => pane.title(),
+    };
+
+    let height = area.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = pane
+        .visible(height)
+        .map(|line| Line::raw(line.as_str()))
+        .collect();
+
+    let scrollback =
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+
+    frame.render_widget(scrollback, area);
+}
+
+fn draw_sidebar(frame: &mut Frame, state: &UiState, area: Rect) {
+    let peers = List::new(state.peers().iter().map(ToString::to_string)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Peers ({})", state.peers().len())),
+    );
+
+    frame.render_widget(peers, area);
+}
+
+fn draw_status(frame: &mut Frame, state: &UiState, area: Rect) {
+    let mut status = format!("connected: {}", state.peers().len());
+
+    for (name, (received, size)) in state.downloads() {
+        status.push_str(&format!(" | {name} {}%", received * 100 / (*size).max(1)));
+    }
+
+    status.push_str(" | Tab: panes  PgUp/PgDn: scroll  Esc: quit");
+
+    let status = Paragraph::new(status).style(Style::default().bg(Color::Blue).fg(Color::White));
+
+    frame.render_widget(status, area);
+}
+
+fn draw_input(frame: &mut Frame, state: &UiState, area: Rect) {
+    let prompt_len = INPUT_PROMPT.len() as u16;
+    let cursor = state.input.cursor() as u16;
+
+    // scroll the line horizontally so the cursor stays visible
+    let offset = (cursor + prompt_len + 1).saturating_sub(area.width);
+
+    let input = Paragraph::new(format!("{INPUT_PROMPT}{}", state.input.text())).scroll((0, offset));
+
+    frame.render_widget(input, area);
+    frame.set_cursor_position((area.x + prompt_len + cursor - offset, area.y));
+}
@@ -0,0 +1,258 @@
+use p2p_chat::models::client::signals::Signals;
+use p2p_chat::models::client::{Exit, Output, shutdown};
+use p2p_chat::models::common::errors::NodeError;
+use p2p_chat::models::common::notification::Notification;
+use p2p_chat::models::common::topic_key::TopicKey;
+use p2p_chat::{NodeEvents, NodeHandle};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// How long a sent file is offered for when `--linger` isn't given
+pub const FILE_LINGER: Duration = Duration::from_secs(60);
+
+/// Message published by `p2p-chat send` without the interactive client
+pub struct OneShot {
+    pub topic: String,
+    pub text: Option<String>,
+    pub file: Option<PathBuf>,
+    /// passphrase of a private topic
+    pub key: Option<String>,
+    pub min_peers: usize,
+    pub wait: Duration,
+    /// time to stay online after sending, ends early once a peer downloaded the file
+    pub linger: Duration,
+    pub output: Output,
+}
+
+impl OneShot {
+    /// Joins the topic, waits for peers subscribed to it and publishes.
+    /// Gossipsub doesn't confirm delivery: the message is queued on the connections and the
+    /// swarm shutdown keeps them open for half a second to write it out, which a slow link
+    /// may not be enough for. `linger` keeps the node online longer. With a file the node
+    /// stays online until the first peer downloaded it or `linger` passed
+    pub async fn run(self, node: NodeHandle, mut events: NodeEvents) -> Exit {
+        let mut signals = match Signals::new() {
+            Ok(signals) => signals,
+            Err(e) => {
+                log::error!("Couldn't listen for signals: {e}");
+                shutdown(&node).await;
+                return Exit::Failed;
+            }
+        };
+
+        let uploaded = Notify::new();
+        let exit = tokio::select! {
+            exit = self.send(&node, &uploaded) => exit,
+            exit = signals.recv() => exit,
+            // the swarm waits for its events to be read
+            _ = drain(&mut events, &uploaded) => {
+                log::error!("Node stopped on its own");
+                Exit::Failed
+            }
+        };
+
+        drop(events);
+        shutdown(&node).await;
+        exit
+    }
+
+    async fn send(&self, node: &NodeHandle, uploaded: &Notify) -> Exit {
+        let key = match &self.key {
+            Some(passphrase) => match TopicKey::derive_async(&self.topic, passphrase).await {
+                Ok(key) => Some(key),
+                Err(e) => {
+                    self.error(e.to_string());
+                    return Exit::Failed;
+                }
+            },
+            None => None,
+        };
+
+        if let Err(e) = node.subscribe(&self.topic, key).await {
+            self.error(e.to_string());
+            return Exit::Failed;
+        }
+
+        match node
+            .wait_for_topic_peers(&self.topic, self.min_peers, self.wait)
+            .await
+        {
+            Ok(found) => log::info!("{found} peers subscribed to {}", self.topic),
+            Err(e @ NodeError::NotEnoughPeers { .. }) => {
+                self.error(format!("{e} in {}s", self.wait.as_secs()));
+                return Exit::NoPeers;
+            }
+            Err(e) => {
+                self.error(e.to_string());
+                return Exit::Failed;
+            }
+        }
+
+        let result = match &self.file {
+            Some(path) => {
+                node.send_file(Some(&self.topic), path, self.text.clone())
+                    .await
+            }
+            None => {
+                let text = self.text.as_deref().unwrap_or_default();
+                node.publish(Some(&self.topic), text).await
+            }
+        };
+
+        match result {
+            Ok(outcome) => {
+                let label = format!("send --topic {}", self.topic);
+                let lines = self.output.result(&label, &outcome);
+                lines.iter().for_each(|line| println!("{line}"));
+            }
+            Err(e) => {
+                self.error(format!("Couldn't send the message: {e}"));
+                return Exit::Failed;
+            }
+        }
+
+        if self.file.is_some() {
+            log::info!("Offering the file for {:?}", self.linger);
+            if tokio::time::timeout(self.linger, uploaded.notified())
+                .await
+                .is_err()
+            {
+                self.error(format!(
+                    "No peer downloaded the file in {}s",
+                    self.linger.as_secs()
+                ));
+            }
+        } else if !self.linger.is_zero() {
+            log::info!("Staying online for {:?}", self.linger);
+            tokio::time::sleep(self.linger).await;
+        }
+
+        Exit::Quit
+    }
+
+    /// Prints the error to stderr, or as a JSON error line to stdout with the results
+    fn error(&self, line: String) {
+        match self.output {
+            Output::Text => eprintln!("{line}"),
+            Output::Json => self
+                .output
+                .error(vec![line])
+                .iter()
+                .for_each(|line| println!("{line}")),
+        }
+    }
+}
+
+/// Reads the events until the node stops, telling `uploaded` when a peer got the file
+async fn drain(events: &mut NodeEvents, uploaded: &Notify) {
+    while let Some(notification) = events.recv().await {
+        log::debug!("{notification:?}");
+
+        if let Notification::UploadFinished { .. } = notification {
+            uploaded.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p2p_chat::models::common::notification::Notification;
+    use p2p_chat::models::node::test_support::{first_listen_addr, start_local_node};
+
+    fn one_shot(min_peers: usize, wait: Duration) -> OneShot {
+        OneShot {
+            topic: "ops".to_string(),
+            text: Some("deploy done".to_string()),
+            file: None,
+            key: None,
+            min_peers,
+            wait,
+            linger: Duration::ZERO,
+            output: Output::Text,
+        }
+    }
+
+    #[tokio::test]
+    async fn too_few_peers_exit_with_no_peers() {
+        let dir = tempfile::tempdir().unwrap();
+        let node = start_local_node(dir.path(), Vec::new());
+
+        let exit = one_shot(1, Duration::from_millis(300))
+            .run(node.handle, node.events)
+            .await;
+
+        assert_eq!(exit, Exit::NoPeers);
+        assert_eq!(exit.code(), 3);
+    }
+
+    #[tokio::test]
+    async fn message_reaches_the_peer_before_exit() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut receiver = start_local_node(&dir.path().join("receiver"), Vec::new());
+        receiver.handle.subscribe("ops", None).await.unwrap();
+
+        let addr = first_listen_addr(&receiver.handle).await;
+
+        let sender = start_local_node(&dir.path().join("sender"), vec![addr]);
+        let exit = one_shot(1, Duration::from_secs(10))
+            .run(sender.handle, sender.events)
+            .await;
+        assert_eq!(exit, Exit::Quit);
+
+        let received = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(Notification::Message(received)) = receiver.events.recv().await {
+                    break received;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(received.msg.data.as_deref(), Some("deploy done"));
+    }
+
+    #[tokio::test]
+    async fn file_is_downloaded_before_exit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.txt");
+        std::fs::write(&path, "quarterly numbers").unwrap();
+
+        let mut receiver = start_local_node(&dir.path().join("receiver"), Vec::new());
+        receiver.handle.subscribe("ops", None).await.unwrap();
+        let addr = first_listen_addr(&receiver.handle).await;
+
+        let sender = start_local_node(&dir.path().join("sender"), vec![addr]);
+        let one_shot = OneShot {
+            file: Some(path),
+            linger: Duration::from_secs(30),
+            ..one_shot(1, Duration::from_secs(10))
+        };
+        let sending = tokio::spawn(one_shot.run(sender.handle, sender.events));
+
+        let saved = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                match receiver.events.recv().await.unwrap() {
+                    Notification::Message(received) => {
+                        let file = received.msg.file.unwrap();
+                        receiver.handle.download(file.short_id()).await.unwrap();
+                    }
+                    Notification::DownloadFinished { path, .. } => break path,
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(saved).unwrap(), "quarterly numbers");
+
+        // the sender stops after the download instead of lingering
+        let exit = tokio::time::timeout(Duration::from_secs(10), sending)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(exit, Exit::Quit);
+    }
+}